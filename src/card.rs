@@ -0,0 +1,201 @@
+use crate::{Word, WORD_BYTES};
+
+pub const CARD_COLUMNS: usize = 80;
+pub const CARD_WORDS: usize = 16;
+
+// MIX character codes, TAOCP 1.3.1 table 1
+const MIX_CHARS: [char; 56] = [
+    ' ', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'Δ', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q',
+    'R', 'Σ', 'Π', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '0', '1', '2', '3', '4', '5', '6', '7',
+    '8', '9', '.', ',', '(', ')', '+', '-', '*', '/', '=', '$', '<', '>', '@', ';', ':', '\'',
+];
+
+pub fn mix_char_code(c: char) -> Option<u8> {
+    MIX_CHARS.iter().position(|&m| m == c).map(|p| p as u8)
+}
+
+pub fn mix_code_char(code: u8) -> Option<char> {
+    MIX_CHARS.get(code as usize).copied()
+}
+
+/// Pack text into words of five MIX characters, padding the last word
+/// with blanks. None if a character has no MIX code.
+pub fn text_to_words(text: &str) -> Option<Vec<Word>> {
+    let codes: Vec<u8> = text.chars().map(mix_char_code).collect::<Option<_>>()?;
+    Some(
+        codes
+            .chunks(WORD_BYTES)
+            .map(|chunk| {
+                let mut word = Word::default();
+                word.bytes[..chunk.len()].copy_from_slice(chunk);
+                word
+            })
+            .collect(),
+    )
+}
+
+/// Unpack words of five MIX characters, dropping trailing blanks. Bytes
+/// without a character become '?'.
+pub fn words_to_text(words: &[Word]) -> String {
+    let text: String = words
+        .iter()
+        .flat_map(|word| word.bytes)
+        .map(|code| mix_code_char(code).unwrap_or('?'))
+        .collect();
+    text.trim_end_matches(' ').to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    columns: [u8; CARD_COLUMNS],
+}
+
+impl Card {
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut columns = [0; CARD_COLUMNS];
+        for (n, c) in line.chars().enumerate() {
+            if n >= CARD_COLUMNS {
+                return None;
+            }
+            columns[n] = mix_char_code(c)?;
+        }
+        Some(Self { columns })
+    }
+
+    pub fn from_words(words: &[Word]) -> Self {
+        let mut columns = [0; CARD_COLUMNS];
+        for (w, word) in words.iter().take(CARD_WORDS).enumerate() {
+            columns[w * WORD_BYTES..(w + 1) * WORD_BYTES].copy_from_slice(&word.bytes);
+        }
+        Self { columns }
+    }
+
+    pub fn to_words(&self) -> [Word; CARD_WORDS] {
+        let mut words = [Word::default(); CARD_WORDS];
+        for (w, word) in words.iter_mut().enumerate() {
+            word.bytes
+                .copy_from_slice(&self.columns[w * WORD_BYTES..(w + 1) * WORD_BYTES]);
+        }
+        words
+    }
+
+    pub fn to_line(&self) -> String {
+        let line: String = self
+            .columns
+            .iter()
+            .map(|&code| mix_code_char(code).unwrap_or('?'))
+            .collect();
+        line.trim_end().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardDeck {
+    cards: Vec<Card>,
+}
+
+impl CardDeck {
+    pub fn new() -> Self {
+        Self { cards: Vec::new() }
+    }
+
+    pub fn parse(input: &str) -> Self {
+        let mut deck = Self::new();
+        for (line, text) in input.lines().enumerate() {
+            let text = text.trim_end_matches('\r');
+            match Card::from_line(text) {
+                Some(card) => deck.push(card),
+                None => panic!("Invalid card at line {}", line),
+            }
+        }
+        deck
+    }
+
+    pub fn push(&mut self, card: Card) {
+        self.cards.push(card);
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for card in &self.cards {
+            text.push_str(&card.to_line());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mix_char_code() {
+        assert_eq!(mix_char_code(' '), Some(0));
+        assert_eq!(mix_char_code('A'), Some(1));
+        assert_eq!(mix_char_code('Δ'), Some(10));
+        assert_eq!(mix_char_code('0'), Some(30));
+        assert_eq!(mix_char_code('\''), Some(55));
+        assert_eq!(mix_char_code('a'), None);
+    }
+
+    #[test]
+    fn test_mix_code_char() {
+        for code in 0..56 {
+            let c = mix_code_char(code).unwrap();
+            assert_eq!(mix_char_code(c), Some(code));
+        }
+        assert_eq!(mix_code_char(56), None);
+    }
+
+    #[test]
+    fn test_text_words() {
+        let words = text_to_words("HELLO WORLD").unwrap();
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[2].bytes, [4, 0, 0, 0, 0]);
+        assert_eq!(words_to_text(&words), "HELLO WORLD");
+        assert_eq!(text_to_words("hi"), None);
+        assert_eq!(words_to_text(&[Word::from(63)]), "    ?");
+    }
+
+    #[test]
+    fn test_card_words() {
+        let card = Card::from_line("ABCDE0").unwrap();
+        let words = card.to_words();
+        assert_eq!(words[0].bytes, [1, 2, 3, 4, 5]);
+        assert_eq!(words[1].bytes, [30, 0, 0, 0, 0]);
+        assert_eq!(Card::from_words(&words), card);
+    }
+
+    #[test]
+    fn test_card_too_long() {
+        assert!(Card::from_line(&"A".repeat(CARD_COLUMNS + 1)).is_none());
+        assert!(Card::from_line(&"A".repeat(CARD_COLUMNS)).is_some());
+    }
+
+    #[test]
+    fn test_deck_round_trip() {
+        let text = "HELLO WORLD\n 12345 (A+B)\n";
+        let deck = CardDeck::parse(text);
+        assert_eq!(deck.len(), 2);
+        assert_eq!(deck.to_text(), text);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid card at line 1")]
+    fn test_deck_invalid() {
+        CardDeck::parse("OK\nlower\n");
+    }
+}
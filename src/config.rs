@@ -0,0 +1,429 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::{MAX_INDEX_REGISTER, UNITS};
+
+pub const DEFAULT_MEMORY_SIZE: usize = 4000;
+/// The memory sizes a machine can have: at least one word, and no more
+/// than a two-byte address can reach with 64-value bytes.
+pub const MEMORY_SIZES: RangeInclusive<usize> = 1..=4096;
+pub const DEFAULT_INDEX_REGISTERS: usize = MAX_INDEX_REGISTER as usize;
+pub const DEFAULT_BYTE_SIZE: u8 = 64;
+/// The byte sizes TAOCP allows: a byte holds at least 64 and at most 100
+/// values.
+pub const BYTE_SIZES: RangeInclusive<u8> = 64..=100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstructionSet {
+    /// Knuth's MIX 1009 exactly, with index registers rI1..rI6.
+    #[default]
+    Strict,
+    /// Extensions of this simulator, such as index registers rI7..rI9.
+    Extended,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// ADD and SUB keep five bytes of magnitude and turn the overflow
+    /// toggle on when a carry is lost; only a jump clears the toggle.
+    #[default]
+    Mix,
+    /// As `Mix`, but every addition sets or clears the overflow toggle,
+    /// as earlier versions did.
+    Relaxed,
+}
+
+/// Every setting of a machine. `Display` writes it in the TOML subset that
+/// `MachineConfig::parse` reads, so a configuration can be saved and
+/// reused:
+///
+/// ```text
+/// memory_size = 4000
+/// byte_size = 64
+/// index_registers = 9
+/// instruction_set = "strict"
+/// arithmetic = "mix"
+/// check_loaded_code = false
+/// detect_self_clobber = false
+/// taint_tracking = false
+/// io_latency = true
+/// unit_latency.16 = 500
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineConfig {
+    pub(crate) memory_size: usize,
+    pub(crate) byte_size: u8,
+    pub(crate) index_registers: usize,
+    pub(crate) instruction_set: InstructionSet,
+    pub(crate) arithmetic: Arithmetic,
+    pub(crate) check_loaded_code: bool,
+    pub(crate) detect_self_clobber: bool,
+    pub(crate) taint_tracking: bool,
+    pub(crate) io_latency: bool,
+    // busy times that replace those of the units' devices
+    pub(crate) unit_latencies: BTreeMap<u8, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MachineConfig {
+    pub fn new() -> Self {
+        Self {
+            memory_size: DEFAULT_MEMORY_SIZE,
+            byte_size: DEFAULT_BYTE_SIZE,
+            index_registers: DEFAULT_INDEX_REGISTERS,
+            instruction_set: InstructionSet::default(),
+            arithmetic: Arithmetic::default(),
+            check_loaded_code: false,
+            detect_self_clobber: false,
+            taint_tracking: false,
+            io_latency: false,
+            unit_latencies: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_memory_size(mut self, words: usize) -> Self {
+        assert!(
+            MEMORY_SIZES.contains(&words),
+            "Invalid memory size {}",
+            words
+        );
+        self.memory_size = words;
+        self
+    }
+
+    /// Bytes that hold `size` values instead of 64. Programs that follow
+    /// TAOCP's rules for byte size run the same with any of them.
+    pub fn with_byte_size(mut self, size: u8) -> Self {
+        assert!(BYTE_SIZES.contains(&size), "Invalid byte size {}", size);
+        self.byte_size = size;
+        self
+    }
+
+    /// Give the machine index registers rI1..rIcount.
+    pub fn with_index_registers(mut self, count: usize) -> Self {
+        assert!(
+            (1..=DEFAULT_INDEX_REGISTERS).contains(&count),
+            "Invalid index register count {}",
+            count
+        );
+        self.index_registers = count;
+        self
+    }
+
+    pub fn with_instruction_set(mut self, instruction_set: InstructionSet) -> Self {
+        self.instruction_set = instruction_set;
+        self
+    }
+
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    /// Stop with `StopReason::OutsideLoadedCode` when execution from
+    /// memory reaches a word that was not written by
+    /// `load_program_into_memory` or `load_code`, and refuse such
+    /// addresses in `set_pc` and `set_entry_point`.
+    pub fn with_check_loaded_code(mut self, check: bool) -> Self {
+        self.check_loaded_code = check;
+        self
+    }
+
+    /// Record a `ClobberEvent` when code running from memory stores into
+    /// its own instruction or the next one.
+    pub fn with_detect_self_clobber(mut self, detect: bool) -> Self {
+        self.detect_self_clobber = detect;
+        self
+    }
+
+    /// Track which input words each register and memory word was
+    /// computed from.
+    pub fn with_taint_tracking(mut self, enabled: bool) -> Self {
+        self.taint_tracking = enabled;
+        self
+    }
+
+    /// Keep a unit busy for its latency after each IN, OUT and IOC, so
+    /// that JBUS and JRED see it working and the next operation on it
+    /// waits. Without this every transfer finishes at once.
+    pub fn with_io_latency(mut self, enabled: bool) -> Self {
+        self.io_latency = enabled;
+        self
+    }
+
+    /// Busy time of `unit` in MIX time units, in place of the latency of
+    /// its device.
+    pub fn with_unit_latency(mut self, unit: u8, latency: u64) -> Self {
+        assert!(unit < UNITS, "Invalid unit {}", unit);
+        self.unit_latencies.insert(unit, latency);
+        self
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+
+    pub fn byte_size(&self) -> u8 {
+        self.byte_size
+    }
+
+    pub fn index_registers(&self) -> usize {
+        self.index_registers
+    }
+
+    pub fn instruction_set(&self) -> InstructionSet {
+        self.instruction_set
+    }
+
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    pub fn check_loaded_code(&self) -> bool {
+        self.check_loaded_code
+    }
+
+    pub fn detect_self_clobber(&self) -> bool {
+        self.detect_self_clobber
+    }
+
+    pub fn taint_tracking(&self) -> bool {
+        self.taint_tracking
+    }
+
+    pub fn io_latency(&self) -> bool {
+        self.io_latency
+    }
+
+    /// The busy time set for `unit` with `with_unit_latency`, if any.
+    pub fn unit_latency(&self, unit: u8) -> Option<u64> {
+        self.unit_latencies.get(&unit).copied()
+    }
+
+    pub fn parse(input: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::new();
+        for (line, text) in input.lines().enumerate() {
+            let error = |message: &str| ConfigError {
+                line,
+                message: message.to_string(),
+            };
+            let text = match text.find('#') {
+                Some(pos) => &text[..pos],
+                None => text,
+            }
+            .trim();
+            if text.is_empty() {
+                continue;
+            }
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| error("expected key = value"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let flag = || value.parse().map_err(|_| error("expected true or false"));
+            match key {
+                "memory_size" => {
+                    config.memory_size = value
+                        .parse()
+                        .ok()
+                        .filter(|size| MEMORY_SIZES.contains(size))
+                        .ok_or_else(|| error("invalid size"))?;
+                }
+                "byte_size" => {
+                    config.byte_size = value
+                        .parse()
+                        .ok()
+                        .filter(|size| BYTE_SIZES.contains(size))
+                        .ok_or_else(|| error("invalid byte size"))?;
+                }
+                "index_registers" => {
+                    config.index_registers = value
+                        .parse()
+                        .ok()
+                        .filter(|count| (1..=DEFAULT_INDEX_REGISTERS).contains(count))
+                        .ok_or_else(|| error("invalid index register count"))?;
+                }
+                "instruction_set" => {
+                    config.instruction_set = match value {
+                        "\"strict\"" => InstructionSet::Strict,
+                        "\"extended\"" => InstructionSet::Extended,
+                        _ => return Err(error("expected \"strict\" or \"extended\"")),
+                    };
+                }
+                "arithmetic" => {
+                    config.arithmetic = match value {
+                        "\"mix\"" => Arithmetic::Mix,
+                        "\"relaxed\"" => Arithmetic::Relaxed,
+                        _ => return Err(error("expected \"mix\" or \"relaxed\"")),
+                    };
+                }
+                "check_loaded_code" => config.check_loaded_code = flag()?,
+                "detect_self_clobber" => config.detect_self_clobber = flag()?,
+                "taint_tracking" => config.taint_tracking = flag()?,
+                "io_latency" => config.io_latency = flag()?,
+                _ if key.starts_with("unit_latency.") => {
+                    let unit = key["unit_latency.".len()..]
+                        .parse()
+                        .ok()
+                        .filter(|unit| *unit < UNITS)
+                        .ok_or_else(|| error("invalid unit"))?;
+                    let latency = value.parse().map_err(|_| error("invalid latency"))?;
+                    config.unit_latencies.insert(unit, latency);
+                }
+                _ => return Err(error("unknown key")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl fmt::Display for MachineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let instruction_set = match self.instruction_set {
+            InstructionSet::Strict => "strict",
+            InstructionSet::Extended => "extended",
+        };
+        let arithmetic = match self.arithmetic {
+            Arithmetic::Mix => "mix",
+            Arithmetic::Relaxed => "relaxed",
+        };
+        writeln!(f, "memory_size = {}", self.memory_size)?;
+        writeln!(f, "byte_size = {}", self.byte_size)?;
+        writeln!(f, "index_registers = {}", self.index_registers)?;
+        writeln!(f, "instruction_set = \"{}\"", instruction_set)?;
+        writeln!(f, "arithmetic = \"{}\"", arithmetic)?;
+        writeln!(f, "check_loaded_code = {}", self.check_loaded_code)?;
+        writeln!(f, "detect_self_clobber = {}", self.detect_self_clobber)?;
+        writeln!(f, "taint_tracking = {}", self.taint_tracking)?;
+        writeln!(f, "io_latency = {}", self.io_latency)?;
+        for (unit, latency) in &self.unit_latencies {
+            writeln!(f, "unit_latency.{} = {}", unit, latency)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line + 1, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = MachineConfig::default();
+        assert_eq!(config.memory_size(), DEFAULT_MEMORY_SIZE);
+        assert_eq!(config.byte_size(), DEFAULT_BYTE_SIZE);
+        assert_eq!(config.index_registers(), DEFAULT_INDEX_REGISTERS);
+        assert_eq!(config.instruction_set(), InstructionSet::Strict);
+        assert_eq!(config.arithmetic(), Arithmetic::Mix);
+        assert!(!config.check_loaded_code());
+        assert!(!config.io_latency());
+        assert_eq!(config.unit_latency(16), None);
+    }
+
+    #[test]
+    fn test_config_builder() {
+        let config = MachineConfig::new()
+            .with_memory_size(100)
+            .with_index_registers(6)
+            .with_instruction_set(InstructionSet::Extended);
+        assert_eq!(config.memory_size(), 100);
+        assert_eq!(config.index_registers(), 6);
+        assert_eq!(config.instruction_set(), InstructionSet::Extended);
+        assert_eq!(config.clone(), config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid index register count 10")]
+    fn test_config_too_many_index_registers() {
+        MachineConfig::new().with_index_registers(10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid unit 20")]
+    fn test_config_unit_latency_invalid_unit() {
+        MachineConfig::new().with_unit_latency(20, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid memory size 0")]
+    fn test_config_empty_memory() {
+        MachineConfig::new().with_memory_size(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid memory size 4097")]
+    fn test_config_memory_too_large() {
+        MachineConfig::new().with_memory_size(4097);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid byte size 101")]
+    fn test_config_byte_size_too_large() {
+        MachineConfig::new().with_byte_size(101);
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = MachineConfig::new()
+            .with_memory_size(100)
+            .with_byte_size(100)
+            .with_index_registers(3)
+            .with_arithmetic(Arithmetic::Relaxed)
+            .with_taint_tracking(true)
+            .with_io_latency(true)
+            .with_unit_latency(16, 500)
+            .with_unit_latency(0, 0);
+        assert_eq!(MachineConfig::parse(&config.to_string()), Ok(config));
+        for size in [1, 4096] {
+            let config = MachineConfig::new().with_memory_size(size);
+            assert_eq!(MachineConfig::parse(&config.to_string()), Ok(config));
+        }
+    }
+
+    #[test]
+    fn test_config_parse() {
+        let config = MachineConfig::parse(
+            "# saved\ninstruction_set = \"extended\"\ncheck_loaded_code = true # why not\n",
+        )
+        .unwrap();
+        assert_eq!(config.instruction_set(), InstructionSet::Extended);
+        assert!(config.check_loaded_code());
+        assert_eq!(config.memory_size(), DEFAULT_MEMORY_SIZE);
+    }
+
+    #[test]
+    fn test_config_parse_errors() {
+        let error = |input: &str| MachineConfig::parse(input).unwrap_err();
+        assert_eq!(error("memory_size = big").message, "invalid size");
+        assert_eq!(error("memory_size = 0").message, "invalid size");
+        assert_eq!(error("memory_size = 4097").message, "invalid size");
+        assert_eq!(error("\nindex_registers = 0").line, 1);
+        assert_eq!(error("byte_size = 63").message, "invalid byte size");
+        assert_eq!(error("speed = 3").message, "unknown key");
+        assert_eq!(error("unit_latency.20 = 3").message, "invalid unit");
+        assert_eq!(error("unit_latency.3 = -1").message, "invalid latency");
+        assert_eq!(
+            error("taint_tracking = yes").to_string(),
+            "line 1: expected true or false"
+        );
+    }
+}
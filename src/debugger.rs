@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{Instruction, MMix, RegisterName, StopReason, Word};
+
+/// Why `Debugger::step` or `Debugger::cont` returned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugStop {
+    /// One instruction executed without hitting anything.
+    Step,
+    Breakpoint(u64),
+    MemoryWatch {
+        addr: u64,
+        old: Word,
+        new: Word,
+    },
+    RegisterWatch {
+        reg: RegisterName,
+        old: i64,
+        new: i64,
+    },
+    /// The program halted or faulted, or the pc left memory.
+    Finished(StopReason),
+}
+
+/// Single-steps code loaded into a machine's memory, stopping at
+/// breakpoints and when watched memory words or registers change.
+pub struct Debugger<'m> {
+    mmix: &'m mut MMix,
+    breakpoints: BTreeSet<u64>,
+    memory_watches: BTreeSet<u64>,
+    register_watches: Vec<RegisterName>,
+}
+
+impl<'m> Debugger<'m> {
+    pub fn new(mmix: &'m mut MMix) -> Self {
+        Self {
+            mmix,
+            breakpoints: BTreeSet::new(),
+            memory_watches: BTreeSet::new(),
+            register_watches: Vec::new(),
+        }
+    }
+
+    pub fn mmix(&self) -> &MMix {
+        self.mmix
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u64) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u64) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn watch_memory(&mut self, addr: u64) {
+        self.memory_watches.insert(addr);
+    }
+
+    pub fn watch_register(&mut self, reg: RegisterName) {
+        if !self.register_watches.contains(&reg) {
+            self.register_watches.push(reg);
+        }
+    }
+
+    /// The instruction at the pc, without executing it.
+    pub fn next_instruction(&self) -> Option<Instruction> {
+        self.mmix.peek_instruction(self.mmix.pc())
+    }
+
+    pub fn step(&mut self) -> DebugStop {
+        let memory: Vec<(u64, Word)> = self
+            .memory_watches
+            .iter()
+            .map(|&addr| (addr, self.mmix.peek_word(addr).unwrap_or_default()))
+            .collect();
+        let registers: Vec<(RegisterName, i64)> = self
+            .register_watches
+            .iter()
+            .map(|&reg| (reg, self.mmix.register(reg)))
+            .collect();
+        let summary = self.mmix.step();
+        if summary.instructions == 0 || summary.stop_reason != StopReason::EndOfProgram {
+            return DebugStop::Finished(summary.stop_reason);
+        }
+        for (addr, old) in memory {
+            let new = self.mmix.peek_word(addr).unwrap_or_default();
+            if new != old {
+                return DebugStop::MemoryWatch { addr, old, new };
+            }
+        }
+        for (reg, old) in registers {
+            let new = self.mmix.register(reg);
+            if new != old {
+                return DebugStop::RegisterWatch { reg, old, new };
+            }
+        }
+        DebugStop::Step
+    }
+
+    /// Step until a breakpoint, a watch or the end of the program. A
+    /// breakpoint at the starting pc does not stop immediately.
+    pub fn cont(&mut self) -> DebugStop {
+        loop {
+            match self.step() {
+                DebugStop::Step if self.breakpoints.contains(&self.mmix.pc()) => {
+                    return DebugStop::Breakpoint(self.mmix.pc());
+                }
+                DebugStop::Step => {}
+                stop => return stop,
+            }
+        }
+    }
+}
+
+impl fmt::Display for DebugStop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DebugStop::Step => write!(f, "step"),
+            DebugStop::Breakpoint(addr) => write!(f, "breakpoint at {}", addr),
+            DebugStop::MemoryWatch { addr, old, new } => {
+                write!(f, "memory[{}] changed from {} to {}", addr, old, new)
+            }
+            DebugStop::RegisterWatch { reg, old, new } => {
+                write!(f, "{} changed from {} to {}", reg, old, new)
+            }
+            DebugStop::Finished(reason) => write!(f, "finished: {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Program;
+
+    fn loaded(input: &str) -> MMix {
+        let mut program = Program::new(input);
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        mmix.set_pc(100).unwrap();
+        mmix
+    }
+
+    #[test]
+    fn test_step_and_breakpoint() {
+        let mut mmix = loaded("ENTA 1; ENTX 2; ENTA 3; HLT");
+        let mut debugger = Debugger::new(&mut mmix);
+        assert_eq!(debugger.next_instruction(), Some(Instruction::ENTA(1)));
+        assert_eq!(debugger.step(), DebugStop::Step);
+        debugger.add_breakpoint(102);
+        assert_eq!(debugger.cont(), DebugStop::Breakpoint(102));
+        assert_eq!(debugger.next_instruction(), Some(Instruction::ENTA(3)));
+        assert_eq!(debugger.cont(), DebugStop::Finished(StopReason::Halted));
+        assert_eq!(mmix.register(RegisterName::A), 3);
+    }
+
+    #[test]
+    fn test_step_self_jump() {
+        let mut mmix = loaded("L JMP L");
+        let mut debugger = Debugger::new(&mut mmix);
+        assert_eq!(debugger.step(), DebugStop::Step);
+        assert_eq!(debugger.mmix().pc(), 100);
+        debugger.add_breakpoint(100);
+        assert_eq!(debugger.cont(), DebugStop::Breakpoint(100));
+    }
+
+    #[test]
+    fn test_watches() {
+        let mut mmix = loaded("ENTA 7; STA 50; ENTX 4; HLT");
+        let mut debugger = Debugger::new(&mut mmix);
+        debugger.watch_memory(50);
+        debugger.watch_register(RegisterName::X);
+        let stop = debugger.cont();
+        assert_eq!(
+            stop,
+            DebugStop::MemoryWatch {
+                addr: 50,
+                old: Word::default(),
+                new: Word::from(7)
+            }
+        );
+        assert_eq!(
+            stop.to_string(),
+            "memory[50] changed from + 00 00 00 00 00 to + 00 00 00 00 07"
+        );
+        let stop = debugger.cont();
+        assert_eq!(stop.to_string(), "rX changed from 0 to 4");
+        assert_eq!(debugger.mmix().pc(), 103);
+    }
+}
@@ -0,0 +1,580 @@
+//! MIX input-output units, TAOCP 1.3.1.
+//!
+//! Units 0-7 are tapes, 8-15 disks, 16 the card reader, 17 the card punch,
+//! 18 the line printer and 19 the typewriter and paper tape. IN and OUT move
+//! one block of words between memory and a unit; IOC sends it a control
+//! code. A new machine has in-memory devices on every unit, and
+//! `MMix::attach` replaces any of them with another `MixDevice`.
+
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{text_to_words, words_to_text, Card, CardDeck, Word, CARD_WORDS};
+
+pub const UNITS: u8 = 20;
+pub const FIRST_DISK: u8 = 8;
+pub const CARD_READER: u8 = 16;
+pub const CARD_PUNCH: u8 = 17;
+pub const LINE_PRINTER: u8 = 18;
+pub const PAPER_TAPE: u8 = 19;
+
+pub const TAPE_BLOCK: usize = 100;
+pub const DISK_BLOCK: usize = 100;
+pub const PRINTER_BLOCK: usize = 24;
+pub const PAPER_TAPE_BLOCK: usize = 14;
+
+// rough busy times of the units, in MIX time units
+const TAPE_LATENCY: u64 = 1000;
+const DISK_LATENCY: u64 = 500;
+const CARD_LATENCY: u64 = 5000;
+const PRINTER_LATENCY: u64 = 2500;
+const PAPER_TAPE_LATENCY: u64 = 2000;
+
+// the largest 6-bit byte
+const DEFAULT_MAXVAL: u8 = 63;
+
+/// A peripheral attached to one MIX unit.
+///
+/// `rx` is the contents of rX when the instruction runs, which disks use
+/// as the block number. Devices move their data at once; with
+/// `MachineConfig::with_io_latency` the unit then stays busy for its
+/// `latency`, and is never busy otherwise.
+pub trait MixDevice: Any + DeviceClone + Send + Sync {
+    /// Words moved by one IN or OUT.
+    fn block_size(&self) -> usize;
+
+    /// IN: fill `block` with the next block of input. False if there is
+    /// none, leaving `block` unchanged.
+    fn read(&mut self, _block: &mut [Word], _rx: i64) -> bool {
+        false
+    }
+
+    /// OUT: take one block of output.
+    fn write(&mut self, _block: &[Word], _rx: i64) {}
+
+    /// IOC with the effective address `m`.
+    fn control(&mut self, _m: i64, _rx: i64) {}
+
+    /// MIX time units the unit stays busy after an IN, OUT or IOC.
+    fn latency(&self) -> u64 {
+        0
+    }
+}
+
+/// Lets `MMix` clone its devices. Implemented for every `Clone` device.
+pub trait DeviceClone {
+    fn clone_device(&self) -> Box<dyn MixDevice>;
+}
+
+impl<T: MixDevice + Clone> DeviceClone for T {
+    fn clone_device(&self) -> Box<dyn MixDevice> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn MixDevice> {
+    fn clone(&self) -> Self {
+        self.clone_device()
+    }
+}
+
+/// The device a new machine has on `unit`.
+pub(crate) fn default_device(unit: u8) -> Box<dyn MixDevice> {
+    match unit {
+        CARD_READER => Box::new(CardReader::new()),
+        CARD_PUNCH => Box::new(CardPunch::new()),
+        LINE_PRINTER => Box::new(LinePrinter::new()),
+        PAPER_TAPE => Box::new(PaperTape::new()),
+        _ if unit < FIRST_DISK => Box::new(Tape::new()),
+        _ => Box::new(Disk::new()),
+    }
+}
+
+pub(crate) fn downcast<T: MixDevice>(device: &dyn MixDevice) -> Option<&T> {
+    (device as &dyn Any).downcast_ref()
+}
+
+pub(crate) fn downcast_mut<T: MixDevice>(device: &mut dyn MixDevice) -> Option<&mut T> {
+    (device as &mut dyn Any).downcast_mut()
+}
+
+/// A magnetic tape of 100-word blocks. Writing a block erases the rest of
+/// the tape. IOC 0 rewinds, any other M skips M blocks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tape {
+    blocks: Vec<Vec<Word>>,
+    position: usize,
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_blocks(blocks: Vec<Vec<Word>>) -> Self {
+        Self {
+            blocks,
+            position: 0,
+        }
+    }
+
+    pub fn blocks(&self) -> &[Vec<Word>] {
+        &self.blocks
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl MixDevice for Tape {
+    fn block_size(&self) -> usize {
+        TAPE_BLOCK
+    }
+
+    fn latency(&self) -> u64 {
+        TAPE_LATENCY
+    }
+
+    fn read(&mut self, block: &mut [Word], _rx: i64) -> bool {
+        let Some(words) = self.blocks.get(self.position) else {
+            return false;
+        };
+        block.fill(Word::default());
+        block[..words.len()].copy_from_slice(words);
+        self.position += 1;
+        true
+    }
+
+    fn write(&mut self, block: &[Word], _rx: i64) {
+        self.blocks.truncate(self.position);
+        self.blocks.push(block.to_vec());
+        self.position += 1;
+    }
+
+    fn control(&mut self, m: i64, _rx: i64) {
+        self.position = if m == 0 {
+            0
+        } else {
+            (self.position as i64 + m).clamp(0, self.blocks.len() as i64) as usize
+        };
+    }
+}
+
+/// A disk or drum of 100-word blocks, addressed by rX. Blocks never
+/// written read as zeros.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Disk {
+    blocks: BTreeMap<i64, Vec<Word>>,
+}
+
+impl Disk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block(&self, n: i64) -> Option<&[Word]> {
+        self.blocks.get(&n).map(Vec::as_slice)
+    }
+
+    pub fn set_block(&mut self, n: i64, words: &[Word]) {
+        let mut block = vec![Word::default(); DISK_BLOCK];
+        block[..words.len()].copy_from_slice(words);
+        self.blocks.insert(n, block);
+    }
+}
+
+impl MixDevice for Disk {
+    fn block_size(&self) -> usize {
+        DISK_BLOCK
+    }
+
+    fn latency(&self) -> u64 {
+        DISK_LATENCY
+    }
+
+    fn read(&mut self, block: &mut [Word], rx: i64) -> bool {
+        match self.blocks.get(&rx) {
+            Some(words) => block.copy_from_slice(words),
+            None => block.fill(Word::default()),
+        }
+        true
+    }
+
+    fn write(&mut self, block: &[Word], rx: i64) {
+        self.set_block(rx, block);
+    }
+}
+
+/// The card reader, holding the cards not yet read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardReader {
+    cards: VecDeque<Card>,
+}
+
+impl CardReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&mut self, deck: &CardDeck) {
+        self.cards.extend(deck.cards().iter().cloned());
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cards.len()
+    }
+}
+
+impl MixDevice for CardReader {
+    fn block_size(&self) -> usize {
+        CARD_WORDS
+    }
+
+    fn latency(&self) -> u64 {
+        CARD_LATENCY
+    }
+
+    fn read(&mut self, block: &mut [Word], _rx: i64) -> bool {
+        match self.cards.pop_front() {
+            Some(card) => {
+                block.copy_from_slice(&card.to_words());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The card punch, holding the cards punched so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardPunch {
+    deck: CardDeck,
+}
+
+impl CardPunch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn deck(&self) -> &CardDeck {
+        &self.deck
+    }
+}
+
+impl MixDevice for CardPunch {
+    fn block_size(&self) -> usize {
+        CARD_WORDS
+    }
+
+    fn latency(&self) -> u64 {
+        CARD_LATENCY
+    }
+
+    fn write(&mut self, block: &[Word], _rx: i64) {
+        self.deck.push(Card::from_words(block));
+    }
+}
+
+/// The line printer, 120 characters a line. IOC 0 starts a new page,
+/// written as a form feed.
+///
+/// What a program printed stays in the printer, so a run can be checked
+/// without touching stdout:
+///
+/// ```
+/// use checksmix::{text_to_words, LinePrinter, MMix, Program, LINE_PRINTER};
+///
+/// let mut mmix = MMix::new();
+/// let message = text_to_words("HELLO, WORLD").unwrap();
+/// mmix.write_memory(100, &message).unwrap();
+/// let mut program = Program::new("OUT 100(18)\nHLT");
+/// program.parse();
+/// mmix.execute(&program);
+/// let printer = mmix.device::<LinePrinter>(LINE_PRINTER).unwrap();
+/// assert_eq!(printer.text(), "HELLO, WORLD\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinePrinter {
+    text: String,
+}
+
+impl LinePrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl MixDevice for LinePrinter {
+    fn block_size(&self) -> usize {
+        PRINTER_BLOCK
+    }
+
+    fn latency(&self) -> u64 {
+        PRINTER_LATENCY
+    }
+
+    fn write(&mut self, block: &[Word], _rx: i64) {
+        self.text.push_str(&words_to_text(block));
+        self.text.push('\n');
+    }
+
+    fn control(&mut self, m: i64, _rx: i64) {
+        if m == 0 {
+            self.text.push('\x0c');
+        }
+    }
+}
+
+/// Unit 19: input from paper tape, 70 characters a line, and output to the
+/// typewriter. IOC 0 rewinds the paper tape.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaperTape {
+    lines: Vec<Vec<Word>>,
+    position: usize,
+    typed: String,
+}
+
+impl PaperTape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(input: &str) -> Self {
+        let mut tape = Self::new();
+        for (line, text) in input.lines().enumerate() {
+            match text_to_words(text.trim_end_matches('\r')) {
+                Some(words) if words.len() <= PAPER_TAPE_BLOCK => tape.lines.push(words),
+                _ => panic!("Invalid paper tape at line {}", line),
+            }
+        }
+        tape
+    }
+
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+}
+
+impl MixDevice for PaperTape {
+    fn block_size(&self) -> usize {
+        PAPER_TAPE_BLOCK
+    }
+
+    fn latency(&self) -> u64 {
+        PAPER_TAPE_LATENCY
+    }
+
+    fn read(&mut self, block: &mut [Word], _rx: i64) -> bool {
+        let Some(words) = self.lines.get(self.position) else {
+            return false;
+        };
+        // blank padding, so the line reads back as typed
+        block.fill(Word::default());
+        block[..words.len()].copy_from_slice(words);
+        self.position += 1;
+        true
+    }
+
+    fn write(&mut self, block: &[Word], _rx: i64) {
+        self.typed.push_str(&words_to_text(block));
+        self.typed.push('\n');
+    }
+
+    fn control(&mut self, m: i64, _rx: i64) {
+        if m == 0 {
+            self.position = 0;
+        }
+    }
+}
+
+/// A frame buffer of `width` by `height` pixels, one word each, with
+/// bytes 3, 4 and 5 as red, green and blue. A block is one row: each OUT
+/// draws the next row, rows past the bottom are dropped, and IOC 0
+/// returns to the top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Word>,
+    row: usize,
+    maxval: u8,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0, "Invalid frame buffer size");
+        Self {
+            width,
+            height,
+            pixels: vec![Word::default(); width * height],
+            row: 0,
+            maxval: DEFAULT_MAXVAL,
+        }
+    }
+
+    /// The brightest value of a color byte, 63 by default to match 6-bit
+    /// bytes. Larger bytes are shown at full brightness.
+    pub fn with_maxval(mut self, maxval: u8) -> Self {
+        assert!(maxval > 0, "Invalid maxval");
+        self.maxval = maxval;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn maxval(&self) -> u8 {
+        self.maxval
+    }
+
+    /// The row the next OUT draws.
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    /// The red, green and blue of the pixel in column `x` of row `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let bytes = self.pixels[y * self.width + x].bytes;
+        [bytes[2], bytes[3], bytes[4]].map(|byte| byte.min(self.maxval))
+    }
+
+    /// The picture as a binary PPM (P6) image.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n{}\n", self.width, self.height, self.maxval).into_bytes();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                ppm.extend(self.pixel(x, y));
+            }
+        }
+        ppm
+    }
+}
+
+impl MixDevice for FrameBuffer {
+    fn block_size(&self) -> usize {
+        self.width
+    }
+
+    fn write(&mut self, block: &[Word], _rx: i64) {
+        if self.row < self.height {
+            let start = self.row * self.width;
+            self.pixels[start..start + self.width].copy_from_slice(block);
+            self.row += 1;
+        }
+    }
+
+    fn control(&mut self, m: i64, _rx: i64) {
+        if m == 0 {
+            self.row = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tape() {
+        let mut tape = Tape::new();
+        let mut block = [Word::default(); TAPE_BLOCK];
+        assert!(!tape.read(&mut block, 0));
+        tape.write(&[Word::from(1); TAPE_BLOCK], 0);
+        tape.write(&[Word::from(2); TAPE_BLOCK], 0);
+        tape.write(&[Word::from(3); TAPE_BLOCK], 0);
+        tape.control(-2, 0);
+        tape.write(&[Word::from(4); TAPE_BLOCK], 0);
+        assert_eq!(tape.blocks().len(), 2);
+        tape.control(0, 0);
+        assert!(tape.read(&mut block, 0));
+        assert_eq!(block[0], Word::from(1));
+        assert!(tape.read(&mut block, 0));
+        assert_eq!(block[0], Word::from(4));
+        assert!(!tape.read(&mut block, 0));
+    }
+
+    #[test]
+    fn test_disk() {
+        let mut disk = Disk::new();
+        let mut block = [Word::from(7); DISK_BLOCK];
+        disk.write(&[Word::from(5); DISK_BLOCK], 3);
+        assert!(disk.read(&mut block, 2));
+        assert_eq!(block, [Word::default(); DISK_BLOCK]);
+        assert!(disk.read(&mut block, 3));
+        assert_eq!(block, [Word::from(5); DISK_BLOCK]);
+    }
+
+    #[test]
+    fn test_line_printer() {
+        let mut printer = LinePrinter::new();
+        let mut block = [Word::default(); PRINTER_BLOCK];
+        let words = text_to_words("HELLO").unwrap();
+        block[..words.len()].copy_from_slice(&words);
+        printer.write(&block, 0);
+        printer.control(0, 0);
+        printer.write(&block, 0);
+        assert_eq!(printer.text(), "HELLO\n\x0cHELLO\n");
+    }
+
+    #[test]
+    fn test_paper_tape() {
+        let mut tape = PaperTape::parse("ABC\nXYZ");
+        let mut block = [Word::default(); PAPER_TAPE_BLOCK];
+        assert!(tape.read(&mut block, 0));
+        assert_eq!(words_to_text(&block), "ABC");
+        tape.control(0, 0);
+        assert!(tape.read(&mut block, 0));
+        assert_eq!(words_to_text(&block), "ABC");
+        tape.write(&block, 0);
+        assert_eq!(tape.typed(), "ABC\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid paper tape at line 1")]
+    fn test_paper_tape_invalid() {
+        PaperTape::parse("OK\nlower case");
+    }
+
+    #[test]
+    fn test_frame_buffer() {
+        let pixel = |r, g, b| Word {
+            negative: false,
+            bytes: [0, 0, r, g, b],
+        };
+        let mut screen = FrameBuffer::new(2, 2);
+        assert_eq!(screen.block_size(), 2);
+        screen.write(&[pixel(63, 0, 0), pixel(0, 63, 0)], 0);
+        screen.write(&[pixel(0, 0, 63), pixel(1, 2, 3)], 0);
+        screen.write(&[pixel(9, 9, 9), pixel(9, 9, 9)], 0);
+        assert_eq!(screen.row(), 2);
+        screen.control(0, 0);
+        screen.write(&[pixel(5, 6, 7), pixel(0, 63, 0)], 0);
+        assert_eq!(screen.row(), 1);
+        assert_eq!(screen.pixel(0, 0), [5, 6, 7]);
+        assert_eq!(screen.pixel(1, 1), [1, 2, 3]);
+        let mut ppm = b"P6\n2 2\n63\n".to_vec();
+        ppm.extend([5, 6, 7, 0, 63, 0, 0, 0, 63, 1, 2, 3]);
+        assert_eq!(screen.to_ppm(), ppm);
+        let dim = screen.clone().with_maxval(4);
+        assert_eq!(dim.pixel(0, 0), [4, 4, 4]);
+        assert!(dim.to_ppm().starts_with(b"P6\n2 2\n4\n"));
+    }
+
+    #[test]
+    fn test_downcast() {
+        let device = default_device(LINE_PRINTER);
+        assert!(downcast::<LinePrinter>(device.as_ref()).is_some());
+        assert!(downcast::<Tape>(device.as_ref()).is_none());
+        assert_eq!(device.clone().block_size(), PRINTER_BLOCK);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::{Instruction, MMix, Word, DEFAULT_BYTE_SIZE};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListingFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct Disassembler {
+    format: ListingFormat,
+    raw_bytes: bool,
+    byte_size: u8,
+}
+
+impl Default for Disassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Disassembler {
+    pub fn new() -> Self {
+        Self {
+            format: ListingFormat::default(),
+            raw_bytes: false,
+            byte_size: DEFAULT_BYTE_SIZE,
+        }
+    }
+
+    pub fn format(mut self, format: ListingFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Include the sign and bytes of each word, as in `+ 31 16 00 05 08`.
+    pub fn raw_bytes(mut self, raw_bytes: bool) -> Self {
+        self.raw_bytes = raw_bytes;
+        self
+    }
+
+    /// Decode words from a machine whose bytes hold `size` values.
+    pub fn byte_size(mut self, size: u8) -> Self {
+        self.byte_size = size;
+        self
+    }
+
+    fn mnemonic(&self, word: Word) -> String {
+        match Instruction::from_word(word, self.byte_size) {
+            Some(instruction) => instruction.to_string(),
+            None => format!("CON {}", word.value(self.byte_size)),
+        }
+    }
+
+    pub fn disassemble(&self, origin: u64, words: &[Word]) -> String {
+        let mut listing = String::new();
+        if self.format == ListingFormat::Json {
+            listing.push_str("[\n");
+        }
+        for (offset, &word) in words.iter().enumerate() {
+            let addr = origin + offset as u64;
+            let separator = if offset + 1 < words.len() { "," } else { "" };
+            match (self.format, self.raw_bytes) {
+                (ListingFormat::Text, false) => {
+                    writeln!(listing, "{:04}: {}", addr, self.mnemonic(word))
+                }
+                (ListingFormat::Text, true) => {
+                    writeln!(listing, "{:04}: {}  {}", addr, word, self.mnemonic(word))
+                }
+                (ListingFormat::Json, false) => writeln!(
+                    listing,
+                    "  {{\"address\": {}, \"word\": {}, \"instruction\": \"{}\"}}{}",
+                    addr,
+                    word.value(self.byte_size),
+                    self.mnemonic(word),
+                    separator
+                ),
+                (ListingFormat::Json, true) => writeln!(
+                    listing,
+                    "  {{\"address\": {}, \"word\": {}, \"bytes\": \"{}\", \"instruction\": \"{}\"}}{}",
+                    addr,
+                    word.value(self.byte_size),
+                    word,
+                    self.mnemonic(word),
+                    separator
+                ),
+            }
+            .unwrap();
+        }
+        if self.format == ListingFormat::Json {
+            listing.push_str("]\n");
+        }
+        listing
+    }
+
+    /// Disassemble `range`, stopping at the end of memory. The machine's
+    /// byte size replaces the one set here.
+    pub fn disassemble_memory(&self, mmix: &MMix, range: Range<u64>) -> String {
+        let start = range.start;
+        let words: Vec<Word> = range.map_while(|addr| mmix.peek_word(addr)).collect();
+        self.clone()
+            .byte_size(mmix.config().byte_size())
+            .disassemble(start, &words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Field;
+
+    fn words() -> Vec<Word> {
+        vec![
+            Instruction::LDA(2000, Field::WORD)
+                .to_word(DEFAULT_BYTE_SIZE)
+                .unwrap(),
+            Instruction::ENNA(5).to_word(DEFAULT_BYTE_SIZE).unwrap(),
+            Word::from(-7),
+        ]
+    }
+
+    #[test]
+    fn test_disassemble_text() {
+        let listing = Disassembler::new().disassemble(100, &words());
+        assert_eq!(listing, "0100: LDA 2000\n0101: ENNA 5\n0102: CON -7\n");
+    }
+
+    #[test]
+    fn test_disassemble_raw_bytes() {
+        let listing = Disassembler::new()
+            .raw_bytes(true)
+            .disassemble(100, &words());
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], "0100: + 31 16 00 05 08  LDA 2000");
+        assert_eq!(lines[2], "0102: - 00 00 00 00 07  CON -7");
+    }
+
+    #[test]
+    fn test_disassemble_json() {
+        let listing = Disassembler::new()
+            .format(ListingFormat::Json)
+            .disassemble(0, &words()[..2]);
+        assert_eq!(
+            listing,
+            format!(
+                "[\n  {{\"address\": 0, \"word\": {}, \"instruction\": \"LDA 2000\"}},\n  {{\"address\": 1, \"word\": {}, \"instruction\": \"ENNA 5\"}}\n]\n",
+                i64::from(words()[0]),
+                i64::from(words()[1])
+            )
+        );
+    }
+
+    #[test]
+    fn test_disassemble_memory() {
+        let mut mmix = MMix::new();
+        mmix.write_memory(10, &words()).unwrap();
+        let listing = Disassembler::new().disassemble_memory(&mmix, 10..12);
+        assert_eq!(listing, "0010: LDA 2000\n0011: ENNA 5\n");
+        let listing = Disassembler::new().disassemble_memory(&mmix, 3999..4010);
+        assert_eq!(listing, "3999: NOP\n");
+    }
+
+    #[test]
+    fn test_disassemble_byte_size() {
+        let word = Instruction::LDA(9000, Field::WORD).to_word(100).unwrap();
+        let listing = Disassembler::new()
+            .byte_size(100)
+            .raw_bytes(true)
+            .disassemble(0, &[word]);
+        assert_eq!(listing, "0000: + 90 00 00 05 08  LDA 9000\n");
+    }
+}
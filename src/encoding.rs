@@ -0,0 +1,544 @@
+use crate::{Field, IndexReg, Instruction, RegisterTest, Word, DEFAULT_BYTE_SIZE, UNITS};
+
+const BYTE_SIZE: i64 = DEFAULT_BYTE_SIZE as i64;
+const WORD_LIMIT: i64 = BYTE_SIZE * BYTE_SIZE * BYTE_SIZE * BYTE_SIZE * BYTE_SIZE;
+
+/// Largest magnitude of a five-byte MIX word with 64-value bytes.
+pub const MAX_WORD: i64 = WORD_LIMIT - 1;
+
+// F bytes that select an operation rather than a field
+const FIELD_SLA: u8 = 0;
+const FIELD_SRA: u8 = 1;
+const FIELD_SLAX: u8 = 2;
+const FIELD_SRAX: u8 = 3;
+const FIELD_SLC: u8 = 4;
+const FIELD_SRC: u8 = 5;
+const FIELD_SLB: u8 = 6;
+const FIELD_SRB: u8 = 7;
+const FIELD_JMP: u8 = 0;
+const FIELD_JSJ: u8 = 1;
+const FIELD_JOV: u8 = 2;
+const FIELD_JNOV: u8 = 3;
+const FIELD_JL: u8 = 4;
+const FIELD_JE: u8 = 5;
+const FIELD_JG: u8 = 6;
+const FIELD_JGE: u8 = 7;
+const FIELD_JNE: u8 = 8;
+const FIELD_JLE: u8 = 9;
+const FIELD_EVEN: u8 = 6;
+const FIELD_ODD: u8 = 7;
+const FIELD_INC: u8 = 0;
+const FIELD_DEC: u8 = 1;
+const FIELD_ENT: u8 = 2;
+const FIELD_ENN: u8 = 3;
+
+const OP_NOP: u8 = 0;
+const OP_ADD: u8 = 1;
+const OP_SUB: u8 = 2;
+const OP_HLT: u8 = 5;
+const OP_SHIFT: u8 = 6;
+const OP_MOVE: u8 = 7;
+const OP_LDA: u8 = 8;
+const OP_LDX: u8 = 15;
+const OP_LDAN: u8 = 16;
+const OP_LDXN: u8 = 23;
+const OP_STA: u8 = 24;
+const OP_STX: u8 = 31;
+const OP_STJ: u8 = 32;
+const OP_STZ: u8 = 33;
+const OP_JBUS: u8 = 34;
+const OP_IOC: u8 = 35;
+const OP_IN: u8 = 36;
+const OP_OUT: u8 = 37;
+const OP_JRED: u8 = 38;
+const OP_JMP: u8 = 39;
+const OP_JA: u8 = 40;
+const OP_JX: u8 = 47;
+const OP_ENTA: u8 = 48;
+const OP_ENTX: u8 = 55;
+const OP_CMPA: u8 = 56;
+const OP_CMPX: u8 = 63;
+
+// the fields of an instruction word `±AA I F C`, before they are fitted
+// to a byte size
+#[derive(Debug, Clone, Copy)]
+struct Parts {
+    negative: bool,
+    address: u64,
+    index: u8,
+    field: u8,
+    opcode: u8,
+}
+
+impl Parts {
+    fn to_word(self, byte_size: u8) -> Option<Word> {
+        let size = byte_size as u64;
+        if self.address >= size * size
+            || [self.index, self.field, self.opcode]
+                .iter()
+                .any(|&byte| byte >= byte_size)
+        {
+            return None;
+        }
+        Some(Word {
+            negative: self.negative,
+            bytes: [
+                (self.address / size) as u8,
+                (self.address % size) as u8,
+                self.index,
+                self.field,
+                self.opcode,
+            ],
+        })
+    }
+
+    fn from_word(word: Word, byte_size: u8) -> Self {
+        let [high, low, index, field, opcode] = word.bytes;
+        Self {
+            negative: word.negative,
+            address: high as u64 * byte_size as u64 + low as u64,
+            index,
+            field,
+            opcode,
+        }
+    }
+}
+
+fn pack(address: i64, field: u8, opcode: u8) -> Option<Parts> {
+    Some(Parts {
+        negative: address < 0,
+        address: address.checked_abs()? as u64,
+        index: 0,
+        field,
+        opcode,
+    })
+}
+
+fn pack_address(address: u64, field: u8, opcode: u8) -> Option<Parts> {
+    pack(i64::try_from(address).ok()?, field, opcode)
+}
+
+fn pack_index(n: &IndexReg, opcode: u8) -> Option<u8> {
+    // the opcodes above rI6 belong to rX
+    if n.is_extended() {
+        return None;
+    }
+    Some(opcode + n.number())
+}
+
+fn pack_unit(unit: u8) -> Option<u8> {
+    (unit < UNITS).then_some(unit)
+}
+
+impl Instruction {
+    /// Encode as a MIX word `±AA I F C`, or None if the operands do not fit.
+    /// Bytes hold 64 values; see `to_word` for other byte sizes.
+    pub fn encode(&self) -> Option<i64> {
+        self.to_word(DEFAULT_BYTE_SIZE).map(i64::from)
+    }
+
+    /// Encode for a machine whose bytes hold `byte_size` values, or None
+    /// if the operands do not fit.
+    pub fn to_word(&self, byte_size: u8) -> Option<Word> {
+        self.parts()?.to_word(byte_size)
+    }
+
+    fn parts(&self) -> Option<Parts> {
+        match self {
+            Instruction::NOP => pack(0, 0, OP_NOP),
+            Instruction::HLT => pack(0, 2, OP_HLT),
+            Instruction::ADD(addr, field) => pack_address(*addr, field.spec(), OP_ADD),
+            Instruction::SUB(addr, field) => pack_address(*addr, field.spec(), OP_SUB),
+            Instruction::LDA(addr, field) => pack_address(*addr, field.spec(), OP_LDA),
+            Instruction::LDX(addr, field) => pack_address(*addr, field.spec(), OP_LDX),
+            Instruction::LDI(n, addr, field) => {
+                pack_address(*addr, field.spec(), pack_index(n, OP_LDA)?)
+            }
+            Instruction::LDAN(addr, field) => pack_address(*addr, field.spec(), OP_LDAN),
+            Instruction::LDXN(addr, field) => pack_address(*addr, field.spec(), OP_LDXN),
+            Instruction::LDIN(n, addr, field) => {
+                pack_address(*addr, field.spec(), pack_index(n, OP_LDAN)?)
+            }
+            Instruction::STA(addr, field) => pack_address(*addr, field.spec(), OP_STA),
+            Instruction::STX(addr, field) => pack_address(*addr, field.spec(), OP_STX),
+            Instruction::STI(n, addr, field) => {
+                pack_address(*addr, field.spec(), pack_index(n, OP_STA)?)
+            }
+            Instruction::STJ(addr, field) => pack_address(*addr, field.spec(), OP_STJ),
+            Instruction::STZ(addr, field) => pack_address(*addr, field.spec(), OP_STZ),
+            Instruction::ENTA(value) => pack(*value, FIELD_ENT, OP_ENTA),
+            Instruction::ENTX(value) => pack(*value, FIELD_ENT, OP_ENTX),
+            Instruction::ENTI(n, value) => pack(*value, FIELD_ENT, pack_index(n, OP_ENTA)?),
+            Instruction::ENNA(value) => pack(*value, FIELD_ENN, OP_ENTA),
+            Instruction::ENNX(value) => pack(*value, FIELD_ENN, OP_ENTX),
+            Instruction::ENNI(n, value) => pack(*value, FIELD_ENN, pack_index(n, OP_ENTA)?),
+            Instruction::INCA(value) => pack(*value, FIELD_INC, OP_ENTA),
+            Instruction::INCX(value) => pack(*value, FIELD_INC, OP_ENTX),
+            Instruction::INCI(n, value) => pack(*value, FIELD_INC, pack_index(n, OP_ENTA)?),
+            Instruction::DECA(value) => pack(*value, FIELD_DEC, OP_ENTA),
+            Instruction::DECX(value) => pack(*value, FIELD_DEC, OP_ENTX),
+            Instruction::DECI(n, value) => pack(*value, FIELD_DEC, pack_index(n, OP_ENTA)?),
+            Instruction::CMPA(addr, field) => pack_address(*addr, field.spec(), OP_CMPA),
+            Instruction::CMPX(addr, field) => pack_address(*addr, field.spec(), OP_CMPX),
+            Instruction::CMPI(n, addr, field) => {
+                pack_address(*addr, field.spec(), pack_index(n, OP_CMPA)?)
+            }
+            Instruction::IN(addr, unit) => pack_address(*addr, pack_unit(*unit)?, OP_IN),
+            Instruction::OUT(addr, unit) => pack_address(*addr, pack_unit(*unit)?, OP_OUT),
+            Instruction::IOC(m, unit) => pack(*m, pack_unit(*unit)?, OP_IOC),
+            Instruction::SLA(count) => pack_address(*count, FIELD_SLA, OP_SHIFT),
+            Instruction::SRA(count) => pack_address(*count, FIELD_SRA, OP_SHIFT),
+            Instruction::SLAX(count) => pack_address(*count, FIELD_SLAX, OP_SHIFT),
+            Instruction::SRAX(count) => pack_address(*count, FIELD_SRAX, OP_SHIFT),
+            Instruction::SLC(count) => pack_address(*count, FIELD_SLC, OP_SHIFT),
+            Instruction::SRC(count) => pack_address(*count, FIELD_SRC, OP_SHIFT),
+            Instruction::SLB(count) => pack_address(*count, FIELD_SLB, OP_SHIFT),
+            Instruction::SRB(count) => pack_address(*count, FIELD_SRB, OP_SHIFT),
+            Instruction::MOVE(addr, count) => pack_address(*addr, *count, OP_MOVE),
+            Instruction::JMP(addr) => pack_address(*addr, FIELD_JMP, OP_JMP),
+            Instruction::JSJ(addr) => pack_address(*addr, FIELD_JSJ, OP_JMP),
+            Instruction::JOV(addr) => pack_address(*addr, FIELD_JOV, OP_JMP),
+            Instruction::JNOV(addr) => pack_address(*addr, FIELD_JNOV, OP_JMP),
+            Instruction::JL(addr) => pack_address(*addr, FIELD_JL, OP_JMP),
+            Instruction::JE(addr) => pack_address(*addr, FIELD_JE, OP_JMP),
+            Instruction::JG(addr) => pack_address(*addr, FIELD_JG, OP_JMP),
+            Instruction::JGE(addr) => pack_address(*addr, FIELD_JGE, OP_JMP),
+            Instruction::JNE(addr) => pack_address(*addr, FIELD_JNE, OP_JMP),
+            Instruction::JLE(addr) => pack_address(*addr, FIELD_JLE, OP_JMP),
+            Instruction::JA(test, addr) => pack_address(*addr, test.spec(), OP_JA),
+            Instruction::JX(test, addr) => pack_address(*addr, test.spec(), OP_JX),
+            Instruction::JAE(addr) => pack_address(*addr, FIELD_EVEN, OP_JA),
+            Instruction::JAO(addr) => pack_address(*addr, FIELD_ODD, OP_JA),
+            Instruction::JXE(addr) => pack_address(*addr, FIELD_EVEN, OP_JX),
+            Instruction::JXO(addr) => pack_address(*addr, FIELD_ODD, OP_JX),
+            Instruction::JI(n, test, addr) => {
+                pack_address(*addr, test.spec(), pack_index(n, OP_JA)?)
+            }
+            Instruction::JBUS(addr, unit) => pack_address(*addr, pack_unit(*unit)?, OP_JBUS),
+            Instruction::JRED(addr, unit) => pack_address(*addr, pack_unit(*unit)?, OP_JRED),
+            Instruction::Indexed(n, instruction) => {
+                if let Instruction::Indexed(..) = **instruction {
+                    return None;
+                }
+                let mut parts = instruction.parts()?;
+                parts.index = pack_index(n, 0)?;
+                Some(parts)
+            }
+        }
+    }
+
+    /// Decode a MIX word produced by `encode`, or None if it is not a
+    /// supported instruction.
+    pub fn decode(word: i64) -> Option<Instruction> {
+        if word.checked_abs()? >= WORD_LIMIT {
+            return None;
+        }
+        Self::from_word(Word::from(word), DEFAULT_BYTE_SIZE)
+    }
+
+    /// Decode a word produced by `to_word` with the same byte size.
+    pub fn from_word(word: Word, byte_size: u8) -> Option<Instruction> {
+        Self::from_parts(Parts::from_word(word, byte_size))
+    }
+
+    fn from_parts(parts: Parts) -> Option<Instruction> {
+        if parts.index != 0 {
+            let n = IndexReg::try_from(parts.index)
+                .ok()
+                .filter(|n| !n.is_extended())?;
+            return match Self::from_parts(Parts { index: 0, ..parts })? {
+                // they have no address to index
+                instruction @ (Instruction::HLT | Instruction::NOP) => Some(instruction),
+                instruction => Some(Instruction::Indexed(n, Box::new(instruction))),
+            };
+        }
+        let (opcode, spec) = (parts.opcode, parts.field);
+        let magnitude = parts.address as i64;
+        let value = if parts.negative {
+            -magnitude
+        } else {
+            magnitude
+        };
+        let addr = (!parts.negative).then_some(parts.address);
+        let reg = |base: u8| IndexReg::try_from(opcode - base).ok();
+        let field = Field::from_spec(spec);
+        match (opcode, spec) {
+            (OP_NOP, _) => Some(Instruction::NOP),
+            (OP_HLT, 2) => Some(Instruction::HLT),
+            (OP_SHIFT, FIELD_SLA) => Some(Instruction::SLA(addr?)),
+            (OP_SHIFT, FIELD_SRA) => Some(Instruction::SRA(addr?)),
+            (OP_SHIFT, FIELD_SLAX) => Some(Instruction::SLAX(addr?)),
+            (OP_SHIFT, FIELD_SRAX) => Some(Instruction::SRAX(addr?)),
+            (OP_SHIFT, FIELD_SLC) => Some(Instruction::SLC(addr?)),
+            (OP_SHIFT, FIELD_SRC) => Some(Instruction::SRC(addr?)),
+            (OP_SHIFT, FIELD_SLB) => Some(Instruction::SLB(addr?)),
+            (OP_SHIFT, FIELD_SRB) => Some(Instruction::SRB(addr?)),
+            (OP_MOVE, _) => Some(Instruction::MOVE(addr?, spec)),
+            (OP_ADD, _) => Some(Instruction::ADD(addr?, field?)),
+            (OP_SUB, _) => Some(Instruction::SUB(addr?, field?)),
+            (OP_LDA, _) => Some(Instruction::LDA(addr?, field?)),
+            (OP_LDX, _) => Some(Instruction::LDX(addr?, field?)),
+            (9..=14, _) => Some(Instruction::LDI(reg(OP_LDA)?, addr?, field?)),
+            (OP_LDAN, _) => Some(Instruction::LDAN(addr?, field?)),
+            (OP_LDXN, _) => Some(Instruction::LDXN(addr?, field?)),
+            (17..=22, _) => Some(Instruction::LDIN(reg(OP_LDAN)?, addr?, field?)),
+            (OP_STA, _) => Some(Instruction::STA(addr?, field?)),
+            (OP_STX, _) => Some(Instruction::STX(addr?, field?)),
+            (25..=30, _) => Some(Instruction::STI(reg(OP_STA)?, addr?, field?)),
+            (OP_STJ, _) => Some(Instruction::STJ(addr?, field?)),
+            (OP_STZ, _) => Some(Instruction::STZ(addr?, field?)),
+            (OP_JBUS, 0..UNITS) => Some(Instruction::JBUS(addr?, spec)),
+            (OP_JRED, 0..UNITS) => Some(Instruction::JRED(addr?, spec)),
+            (OP_JMP, FIELD_JMP) => Some(Instruction::JMP(addr?)),
+            (OP_JMP, FIELD_JSJ) => Some(Instruction::JSJ(addr?)),
+            (OP_JMP, FIELD_JOV) => Some(Instruction::JOV(addr?)),
+            (OP_JMP, FIELD_JNOV) => Some(Instruction::JNOV(addr?)),
+            (OP_JMP, FIELD_JL) => Some(Instruction::JL(addr?)),
+            (OP_JMP, FIELD_JE) => Some(Instruction::JE(addr?)),
+            (OP_JMP, FIELD_JG) => Some(Instruction::JG(addr?)),
+            (OP_JMP, FIELD_JGE) => Some(Instruction::JGE(addr?)),
+            (OP_JMP, FIELD_JNE) => Some(Instruction::JNE(addr?)),
+            (OP_JMP, FIELD_JLE) => Some(Instruction::JLE(addr?)),
+            (OP_JA, FIELD_EVEN) => Some(Instruction::JAE(addr?)),
+            (OP_JA, FIELD_ODD) => Some(Instruction::JAO(addr?)),
+            (OP_JA, _) => Some(Instruction::JA(RegisterTest::from_spec(spec)?, addr?)),
+            (41..=46, _) => Some(Instruction::JI(
+                reg(OP_JA)?,
+                RegisterTest::from_spec(spec)?,
+                addr?,
+            )),
+            (OP_JX, FIELD_EVEN) => Some(Instruction::JXE(addr?)),
+            (OP_JX, FIELD_ODD) => Some(Instruction::JXO(addr?)),
+            (OP_JX, _) => Some(Instruction::JX(RegisterTest::from_spec(spec)?, addr?)),
+            (OP_IOC, 0..UNITS) => Some(Instruction::IOC(value, spec)),
+            (OP_IN, 0..UNITS) => Some(Instruction::IN(addr?, spec)),
+            (OP_OUT, 0..UNITS) => Some(Instruction::OUT(addr?, spec)),
+            (OP_ENTA, FIELD_ENT) => Some(Instruction::ENTA(value)),
+            (OP_ENTX, FIELD_ENT) => Some(Instruction::ENTX(value)),
+            (49..=54, FIELD_ENT) => Some(Instruction::ENTI(reg(OP_ENTA)?, value)),
+            (OP_ENTA, FIELD_ENN) => Some(Instruction::ENNA(value)),
+            (OP_ENTX, FIELD_ENN) => Some(Instruction::ENNX(value)),
+            (49..=54, FIELD_ENN) => Some(Instruction::ENNI(reg(OP_ENTA)?, value)),
+            (OP_ENTA, FIELD_INC) => Some(Instruction::INCA(value)),
+            (OP_ENTX, FIELD_INC) => Some(Instruction::INCX(value)),
+            (49..=54, FIELD_INC) => Some(Instruction::INCI(reg(OP_ENTA)?, value)),
+            (OP_ENTA, FIELD_DEC) => Some(Instruction::DECA(value)),
+            (OP_ENTX, FIELD_DEC) => Some(Instruction::DECX(value)),
+            (49..=54, FIELD_DEC) => Some(Instruction::DECI(reg(OP_ENTA)?, value)),
+            (OP_CMPA, _) => Some(Instruction::CMPA(addr?, field?)),
+            (OP_CMPX, _) => Some(Instruction::CMPX(addr?, field?)),
+            (57..=62, _) => Some(Instruction::CMPI(reg(OP_CMPA)?, addr?, field?)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reg(n: u8) -> IndexReg {
+        IndexReg::try_from(n).unwrap()
+    }
+
+    fn packed(address: i64, field: u8, opcode: u8) -> i64 {
+        let word = pack(address, field, opcode)
+            .unwrap()
+            .to_word(DEFAULT_BYTE_SIZE);
+        i64::from(word.unwrap())
+    }
+
+    fn sign_and_bytes(word: i64) -> (char, [u8; 5]) {
+        let word = Word::from(word);
+        (word.sign(), word.bytes)
+    }
+
+    #[test]
+    fn test_encode_lda() {
+        // LDA 2000 is +31 16 0 5 8 in TAOCP byte notation
+        assert_eq!(
+            Instruction::LDA(2000, Field::WORD).encode(),
+            Some(2000 * BYTE_SIZE * BYTE_SIZE * BYTE_SIZE + 5 * BYTE_SIZE + 8)
+        );
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instructions = vec![
+            Instruction::NOP,
+            Instruction::HLT,
+            Instruction::ADD(100, Field::WORD),
+            Instruction::SUB(100, Field::WORD),
+            Instruction::LDA(100, Field::WORD),
+            Instruction::LDX(100, Field::WORD),
+            Instruction::LDI(reg(1), 100, Field::WORD),
+            Instruction::LDI(reg(6), 100, Field::WORD),
+            Instruction::LDAN(100, Field::WORD),
+            Instruction::LDXN(100, Field::WORD),
+            Instruction::LDIN(reg(3), 100, Field::WORD),
+            Instruction::STA(100, Field::WORD),
+            Instruction::STX(100, Field::WORD),
+            Instruction::STI(reg(2), 100, Field::WORD),
+            Instruction::STJ(100, Field::ADDRESS),
+            Instruction::STZ(100, Field::WORD),
+            Instruction::ENTA(-100),
+            Instruction::ENTX(100),
+            Instruction::ENTI(reg(4), 100),
+            Instruction::ENNA(100),
+            Instruction::ENNX(-100),
+            Instruction::ENNI(reg(5), 100),
+            Instruction::INCA(100),
+            Instruction::INCX(-100),
+            Instruction::INCI(reg(2), 100),
+            Instruction::DECA(100),
+            Instruction::DECX(100),
+            Instruction::DECI(reg(6), -100),
+            Instruction::CMPA(100, Field::WORD),
+            Instruction::CMPX(100, Field::WORD),
+            Instruction::CMPI(reg(6), 100, Field::WORD),
+        ];
+        for instruction in instructions {
+            let word = instruction.encode().unwrap();
+            assert_eq!(Instruction::decode(word), Some(instruction));
+        }
+    }
+
+    #[test]
+    fn test_encode_binary() {
+        let sign_and_bytes = |instruction: Instruction| instruction.to_word(64).unwrap().bytes;
+        assert_eq!(sign_and_bytes(Instruction::SLB(3)), [0, 3, 0, 6, 6]);
+        assert_eq!(sign_and_bytes(Instruction::SRB(3)), [0, 3, 0, 7, 6]);
+        assert_eq!(sign_and_bytes(Instruction::JAE(100)), [1, 36, 0, 6, 40]);
+        assert_eq!(sign_and_bytes(Instruction::JXO(100)), [1, 36, 0, 7, 47]);
+        for instruction in [
+            Instruction::SLB(3),
+            Instruction::SRB(3),
+            Instruction::JAE(100),
+            Instruction::JAO(100),
+            Instruction::JXE(100),
+            Instruction::JXO(100),
+        ] {
+            let word = instruction.encode().unwrap();
+            assert_eq!(Instruction::decode(word), Some(instruction));
+        }
+    }
+
+    #[test]
+    fn test_encode_out_of_range() {
+        assert_eq!(Instruction::LDA(4096, Field::WORD).encode(), None);
+        assert_eq!(Instruction::ENTA(-4096).encode(), None);
+        assert_eq!(Instruction::LDI(reg(7), 100, Field::WORD).encode(), None);
+    }
+
+    #[test]
+    fn test_wrap_word() {
+        let wrap = |exact| {
+            let (word, overflow) = Word::wrap(exact, DEFAULT_BYTE_SIZE);
+            (i64::from(word), overflow)
+        };
+        assert_eq!(wrap(5), (5, false));
+        assert_eq!(wrap(MAX_WORD as i128), (MAX_WORD, false));
+        assert_eq!(wrap(MAX_WORD as i128 + 3), (2, true));
+        assert_eq!(wrap(-(MAX_WORD as i128) - 3), (-2, true));
+    }
+
+    #[test]
+    fn test_encode_byte_size() {
+        // LDA 9999,1(1:3) with bytes of 100 values is +99 99 01 11 08
+        let instruction = Instruction::Indexed(
+            reg(1),
+            Box::new(Instruction::LDA(9999, Field::new(1, 3).unwrap())),
+        );
+        let word = instruction.to_word(100).unwrap();
+        assert_eq!(word.bytes, [99, 99, 1, 11, 8]);
+        assert_eq!(Instruction::from_word(word, 100), Some(instruction));
+        assert_eq!(Instruction::LDA(9999, Field::WORD).encode(), None);
+        assert_eq!(Instruction::MOVE(0, 99).to_word(64), None);
+        assert!(Instruction::MOVE(0, 99).to_word(100).is_some());
+    }
+
+    #[test]
+    fn test_decode_every_opcode() {
+        // every C and F byte: decoded words must re-encode to themselves
+        // (HLT and NOP ignore the address, so use 0), and each supported
+        // opcode must decode for its default field
+        for opcode in 0..BYTE_SIZE as u8 {
+            for field in 0..BYTE_SIZE as u8 {
+                let word = packed(0, field, opcode);
+                if let Some(instruction) = Instruction::decode(word) {
+                    if opcode != OP_NOP {
+                        assert_eq!(instruction.encode(), Some(word), "C={} F={}", opcode, field);
+                    }
+                }
+            }
+            let decodes = |field| Instruction::decode(packed(100, field, opcode)).is_some();
+            let supported = matches!(
+                opcode,
+                OP_NOP..=OP_SUB | OP_HLT..=OP_MOVE | OP_LDA..=OP_STZ | OP_JBUS..=OP_JX | OP_ENTA..=OP_CMPX
+            );
+            let field = match opcode {
+                OP_HLT => 2,
+                OP_STJ => Field::ADDRESS.spec(),
+                OP_ENTA..=OP_ENTX => FIELD_ENT,
+                _ => Field::WORD.spec(),
+            };
+            assert_eq!(decodes(field), supported, "C={}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_encode_io() {
+        // IN 1000(16) is +15 40 0 16 36
+        let word = Instruction::IN(1000, 16).encode().unwrap();
+        assert_eq!(sign_and_bytes(word), ('+', [15, 40, 0, 16, 36]));
+        assert_eq!(Instruction::decode(word), Some(Instruction::IN(1000, 16)));
+        let word = Instruction::IOC(-3, 2).encode().unwrap();
+        assert_eq!(Instruction::decode(word), Some(Instruction::IOC(-3, 2)));
+        assert_eq!(Instruction::OUT(0, 20).encode(), None);
+        assert_eq!(Instruction::decode(packed(0, 20, OP_OUT)), None);
+    }
+
+    #[test]
+    fn test_encode_indexed() {
+        // LDA 2000,2(0:3) is +31 16 2 3 8
+        let instruction = Instruction::Indexed(
+            reg(2),
+            Box::new(Instruction::LDA(2000, Field::new(0, 3).unwrap())),
+        );
+        let word = instruction.encode().unwrap();
+        assert_eq!(sign_and_bytes(word), ('+', [31, 16, 2, 3, 8]));
+        assert_eq!(Instruction::decode(word), Some(instruction));
+        // ENNA -5,1 keeps its sign
+        let instruction = Instruction::Indexed(reg(1), Box::new(Instruction::ENNA(-5)));
+        let word = instruction.encode().unwrap();
+        assert_eq!(sign_and_bytes(word), ('-', [0, 5, 1, 3, 48]));
+        assert_eq!(Instruction::decode(word), Some(instruction));
+        let extended = Instruction::Indexed(reg(7), Box::new(Instruction::LDA(0, Field::WORD)));
+        assert_eq!(extended.encode(), None);
+        assert_eq!(
+            Instruction::decode(packed(0, 5, OP_LDA) + 7 * BYTE_SIZE * BYTE_SIZE),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(Instruction::decode(WORD_LIMIT), None);
+        assert_eq!(
+            Instruction::decode(-Instruction::LDA(1, Field::WORD).encode().unwrap()),
+            None
+        );
+        // (0:6) and (4:2) are not field specifications
+        assert_eq!(Instruction::decode(packed(0, 6, OP_CMPX)), None);
+        assert_eq!(Instruction::decode(packed(0, 34, OP_LDA)), None);
+    }
+
+    #[test]
+    fn test_encode_field() {
+        // LDA 2000(1:3) is +31 16 0 11 8
+        let instruction = Instruction::LDA(2000, Field::new(1, 3).unwrap());
+        let word = instruction.encode().unwrap();
+        assert_eq!(sign_and_bytes(word), ('+', [31, 16, 0, 11, 8]));
+        assert_eq!(Instruction::decode(word), Some(instruction));
+    }
+}
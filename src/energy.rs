@@ -0,0 +1,47 @@
+use crate::Instruction;
+
+/// Energy cost units charged per instruction and per memory access, for
+/// comparing algorithms by an energy proxy rather than by time.
+pub trait EnergyModel {
+    fn instruction(&mut self, instruction: &Instruction) -> u64;
+
+    fn memory_access(&mut self, _addr: u64) -> u64 {
+        0
+    }
+
+    /// Energy for one executed instruction and the memory words it reads
+    /// and writes, such as the whole block of an `IN` or both ends of a
+    /// `MOVE`.
+    fn charge(&mut self, instruction: &Instruction, addrs: &[u64]) -> u64 {
+        let memory: u64 = addrs.iter().map(|&addr| self.memory_access(addr)).sum();
+        self.instruction(instruction) + memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Field;
+
+    struct Flat;
+
+    impl EnergyModel for Flat {
+        fn instruction(&mut self, _instruction: &Instruction) -> u64 {
+            1
+        }
+
+        fn memory_access(&mut self, _addr: u64) -> u64 {
+            10
+        }
+    }
+
+    #[test]
+    fn test_energy_charge() {
+        assert_eq!(Flat.charge(&Instruction::ENTA(1), &[]), 1);
+        assert_eq!(Flat.charge(&Instruction::LDA(100, Field::WORD), &[100]), 11);
+        assert_eq!(
+            Flat.charge(&Instruction::MOVE(100, 2), &[100, 101, 200, 201]),
+            41
+        );
+    }
+}
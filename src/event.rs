@@ -0,0 +1,54 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithEventKind {
+    Overflow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArithEvent {
+    pub pc: usize,
+    pub kind: ArithEventKind,
+}
+
+/// A store, `IN` or `MOVE` into the instruction being executed or the
+/// one after it, while running from memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClobberEvent {
+    pub pc: usize,
+    pub addr: u64,
+}
+
+impl fmt::Display for ArithEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArithEventKind::Overflow => write!(f, "overflow"),
+        }
+    }
+}
+
+impl fmt::Display for ArithEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at pc={}", self.kind, self.pc)
+    }
+}
+
+impl fmt::Display for ClobberEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "store to {} clobbers code at pc={}", self.addr, self.pc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arith_event_display() {
+        let event = ArithEvent {
+            pc: 12,
+            kind: ArithEventKind::Overflow,
+        };
+        assert_eq!(event.to_string(), "overflow at pc=12");
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+use std::ops::ControlFlow;
+
+use crate::{IndexReg, MMix, Program, RegisterName, RunConfig};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    Register(RegisterName, i64),
+    Memory(u64, Vec<i64>),
+    Overflow(bool),
+    Punched(String),
+}
+
+/// Expected post-conditions of a run, written in a small TOML subset:
+///
+/// ```text
+/// max_instructions = 100
+/// rA = 5
+/// rI1 = -2
+/// overflow = false
+/// memory.100 = [1, 2, 3]
+/// punched = "HELLO\nWORLD"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GradeSpec {
+    pub max_instructions: Option<u64>,
+    pub expectations: Vec<Expectation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeSpecError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeReport {
+    pub checks: Vec<Check>,
+}
+
+impl GradeReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| !check.passed())
+    }
+}
+
+fn parse_int(value: &str) -> Option<i64> {
+    value.trim().parse().ok()
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.replace("\\n", "\n"))
+}
+
+fn parse_ints(value: &str) -> Option<Vec<i64>> {
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        inner
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(parse_int)
+            .collect()
+    } else {
+        parse_int(value).map(|value| vec![value])
+    }
+}
+
+fn parse_register(key: &str) -> Option<RegisterName> {
+    match key {
+        "rA" => Some(RegisterName::A),
+        "rX" => Some(RegisterName::X),
+        "rJ" => Some(RegisterName::J),
+        _ => {
+            let n = key.strip_prefix("rI")?.parse::<u8>().ok()?;
+            IndexReg::try_from(n).ok().map(RegisterName::I)
+        }
+    }
+}
+
+impl GradeSpec {
+    pub fn parse(input: &str) -> Result<Self, GradeSpecError> {
+        let mut spec = Self::default();
+        for (line, text) in input.lines().enumerate() {
+            let error = |message: &str| GradeSpecError {
+                line,
+                message: message.to_string(),
+            };
+            let text = match text.find('#') {
+                Some(pos) if !text[..pos].contains('"') => &text[..pos],
+                _ => text,
+            }
+            .trim();
+            if text.is_empty() {
+                continue;
+            }
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| error("expected key = value"))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key == "max_instructions" {
+                let max = parse_int(value).filter(|max| *max >= 0);
+                spec.max_instructions = Some(max.ok_or_else(|| error("invalid count"))? as u64);
+            } else if key == "overflow" {
+                let overflow = value.parse().map_err(|_| error("expected true or false"))?;
+                spec.expectations.push(Expectation::Overflow(overflow));
+            } else if key == "punched" {
+                let text = parse_string(value).ok_or_else(|| error("expected a string"))?;
+                spec.expectations.push(Expectation::Punched(text));
+            } else if let Some(addr) = key.strip_prefix("memory.") {
+                let addr = addr.parse().map_err(|_| error("invalid address"))?;
+                let words = parse_ints(value).ok_or_else(|| error("expected integers"))?;
+                spec.expectations.push(Expectation::Memory(addr, words));
+            } else if let Some(register) = parse_register(key) {
+                let value = parse_int(value).ok_or_else(|| error("expected an integer"))?;
+                spec.expectations
+                    .push(Expectation::Register(register, value));
+            } else {
+                return Err(error("unknown key"));
+            }
+        }
+        Ok(spec)
+    }
+}
+
+pub fn grade(program: &Program, spec: &GradeSpec) -> GradeReport {
+    grade_on(&mut MMix::new(), program, spec)
+}
+
+/// Run `program` on a prepared machine and evaluate `spec` against it.
+pub fn grade_on(mmix: &mut MMix, program: &Program, spec: &GradeSpec) -> GradeReport {
+    let mut config = RunConfig::new();
+    if let Some(max) = spec.max_instructions {
+        config = config.periodic(max + 1, |_, _| ControlFlow::Break(()));
+    }
+    let summary = mmix.execute_with(program, &mut config);
+    let mut checks = Vec::new();
+    if let Some(max) = spec.max_instructions {
+        checks.push(Check {
+            name: "instructions".to_string(),
+            expected: format!("at most {}", max),
+            actual: if summary.instructions <= max {
+                format!("at most {}", max)
+            } else {
+                format!("more than {}", max)
+            },
+        });
+    }
+    for expectation in &spec.expectations {
+        checks.push(match expectation {
+            Expectation::Register(register, value) => Check {
+                name: register.to_string(),
+                expected: value.to_string(),
+                actual: mmix.register(*register).to_string(),
+            },
+            Expectation::Memory(addr, words) => {
+                let start = *addr as usize;
+                let actual: Vec<i64> = (start..start + words.len())
+                    .map(|addr| mmix.memory.get(addr).map_or(0, |word| mmix.value(word)))
+                    .collect();
+                Check {
+                    name: format!("memory[{}]", addr),
+                    expected: format!("{:?}", words),
+                    actual: format!("{:?}", actual),
+                }
+            }
+            Expectation::Overflow(overflow) => Check {
+                name: "overflow".to_string(),
+                expected: overflow.to_string(),
+                actual: mmix.overflow.to_string(),
+            },
+            Expectation::Punched(text) => Check {
+                name: "punched".to_string(),
+                expected: format!("{:?}", text.trim_end()),
+                actual: format!("{:?}", mmix.punched_deck().to_text().trim_end()),
+            },
+        });
+    }
+    GradeReport { checks }
+}
+
+impl fmt::Display for GradeSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl std::error::Error for GradeSpecError {}
+
+impl fmt::Display for GradeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            if check.passed() {
+                writeln!(f, "PASS {}: {}", check.name, check.actual)?;
+            } else {
+                writeln!(
+                    f,
+                    "FAIL {}: expected {}, got {}",
+                    check.name, check.expected, check.actual
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(input: &str) -> Program {
+        let mut program = Program::new(input);
+        program.parse();
+        program
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let spec = GradeSpec::parse(
+            "# expected\nmax_instructions = 10\nrA = 5\nrI2 = -1\noverflow = false\nmemory.100 = [1, 2]\npunched = \"A # B\"\n",
+        )
+        .unwrap();
+        assert_eq!(spec.max_instructions, Some(10));
+        assert_eq!(
+            spec.expectations,
+            vec![
+                Expectation::Register(RegisterName::A, 5),
+                Expectation::Register(RegisterName::I(IndexReg::try_from(2).unwrap()), -1),
+                Expectation::Overflow(false),
+                Expectation::Memory(100, vec![1, 2]),
+                Expectation::Punched("A # B".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_error() {
+        let err = GradeSpec::parse("rA = 1\nrQ = 2\n").unwrap_err();
+        assert_eq!(err.line, 1);
+        assert_eq!(err.to_string(), "unknown key at line 1");
+    }
+
+    #[test]
+    fn test_grade_pass() {
+        let spec = GradeSpec::parse("rA = 7\nmemory.100 = [7, 0]\nmax_instructions = 3").unwrap();
+        let report = grade(&program("ENTA 7; STA 100; HLT"), &spec);
+        assert!(report.passed(), "{}", report);
+    }
+
+    #[test]
+    fn test_grade_fail() {
+        let spec = GradeSpec::parse("rA = 8\nmax_instructions = 1").unwrap();
+        let report = grade(&program("ENTA 7; STA 100; HLT"), &spec);
+        assert!(!report.passed());
+        let failures: Vec<&str> = report.failures().map(|c| c.name.as_str()).collect();
+        assert_eq!(failures, vec!["instructions", "rA"]);
+        assert!(report.to_string().contains("FAIL rA: expected 8, got 7"));
+    }
+}
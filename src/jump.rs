@@ -0,0 +1,97 @@
+use std::fmt;
+
+/// The sign test of a register jump such as JAN or J1NZ, in the order of
+/// its F byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterTest {
+    Negative,
+    Zero,
+    Positive,
+    NonNegative,
+    NonZero,
+    NonPositive,
+}
+
+pub const REGISTER_TESTS: [RegisterTest; 6] = [
+    RegisterTest::Negative,
+    RegisterTest::Zero,
+    RegisterTest::Positive,
+    RegisterTest::NonNegative,
+    RegisterTest::NonZero,
+    RegisterTest::NonPositive,
+];
+
+impl RegisterTest {
+    pub fn holds(&self, value: i64) -> bool {
+        match self {
+            RegisterTest::Negative => value < 0,
+            RegisterTest::Zero => value == 0,
+            RegisterTest::Positive => value > 0,
+            RegisterTest::NonNegative => value >= 0,
+            RegisterTest::NonZero => value != 0,
+            RegisterTest::NonPositive => value <= 0,
+        }
+    }
+
+    /// The test that holds exactly when this one does not.
+    pub fn negated(&self) -> Self {
+        match self {
+            RegisterTest::Negative => RegisterTest::NonNegative,
+            RegisterTest::Zero => RegisterTest::NonZero,
+            RegisterTest::Positive => RegisterTest::NonPositive,
+            RegisterTest::NonNegative => RegisterTest::Negative,
+            RegisterTest::NonZero => RegisterTest::Zero,
+            RegisterTest::NonPositive => RegisterTest::Positive,
+        }
+    }
+
+    /// The test named by a mnemonic suffix such as "NN".
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        REGISTER_TESTS
+            .into_iter()
+            .find(|test| test.to_string() == suffix)
+    }
+
+    pub(crate) fn from_spec(spec: u8) -> Option<Self> {
+        REGISTER_TESTS.get(spec as usize).copied()
+    }
+
+    pub(crate) fn spec(&self) -> u8 {
+        *self as u8
+    }
+}
+
+impl fmt::Display for RegisterTest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegisterTest::Negative => write!(f, "N"),
+            RegisterTest::Zero => write!(f, "Z"),
+            RegisterTest::Positive => write!(f, "P"),
+            RegisterTest::NonNegative => write!(f, "NN"),
+            RegisterTest::NonZero => write!(f, "NZ"),
+            RegisterTest::NonPositive => write!(f, "NP"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_test() {
+        for (spec, test) in REGISTER_TESTS.into_iter().enumerate() {
+            assert_eq!(RegisterTest::from_spec(spec as u8), Some(test));
+            assert_eq!(RegisterTest::from_suffix(&test.to_string()), Some(test));
+        }
+        assert_eq!(RegisterTest::from_spec(6), None);
+        assert_eq!(RegisterTest::from_suffix("X"), None);
+        let holds = |test: RegisterTest| [-1, 0, 1].map(|value| test.holds(value));
+        assert_eq!(holds(RegisterTest::Negative), [true, false, false]);
+        assert_eq!(holds(RegisterTest::NonZero), [true, false, true]);
+        assert_eq!(holds(RegisterTest::NonPositive), [true, true, false]);
+        for test in REGISTER_TESTS {
+            assert_eq!(holds(test.negated()), holds(test).map(|held| !held));
+        }
+    }
+}
@@ -1,803 +1,5028 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::ControlFlow;
+use std::ops::Range;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
 use lyn::Scanner;
 
+mod card;
+mod config;
+mod debugger;
+mod device;
+mod disasm;
+mod encoding;
+mod energy;
+mod event;
+mod grade;
+mod jump;
+mod memory;
+mod optimize;
+pub mod pipeline;
+mod register;
+mod run;
+mod stats;
+mod summary;
+mod taint;
+pub mod testgen;
+mod trace;
+mod word;
+
+pub use card::{
+    mix_char_code, mix_code_char, text_to_words, words_to_text, Card, CardDeck, CARD_COLUMNS,
+    CARD_WORDS,
+};
+pub use config::{
+    Arithmetic, ConfigError, InstructionSet, MachineConfig, BYTE_SIZES, DEFAULT_BYTE_SIZE,
+    DEFAULT_INDEX_REGISTERS, DEFAULT_MEMORY_SIZE, MEMORY_SIZES,
+};
+pub use debugger::{DebugStop, Debugger};
+pub use device::{
+    CardPunch, CardReader, DeviceClone, Disk, FrameBuffer, LinePrinter, MixDevice, PaperTape, Tape,
+    CARD_PUNCH, CARD_READER, LINE_PRINTER, PAPER_TAPE, UNITS,
+};
+pub use disasm::{Disassembler, ListingFormat};
+pub use encoding::MAX_WORD;
+pub use energy::EnergyModel;
+pub use event::{ArithEvent, ArithEventKind, ClobberEvent};
+pub use grade::{grade, grade_on, Check, Expectation, GradeReport, GradeSpec, GradeSpecError};
+pub use jump::{RegisterTest, REGISTER_TESTS};
+pub use memory::AddressError;
+pub use optimize::OptimizeReport;
+pub use register::{
+    IndexReg, InvalidIndexReg, RegisterName, Registers, MAX_INDEX_REGISTER, STRICT_INDEX_REGISTERS,
+};
+pub use run::RunConfig;
+pub use stats::{OpcodeFamily, OpcodeStats, OPCODE_FAMILIES};
+pub use summary::{Assertion, IoEvent, IoKind, RunSummary, StopReason};
+pub use taint::Origins;
+pub use trace::{TraceEvent, TraceLog, Tracer};
+pub use word::{Field, Word, WORD_BYTES};
+
+use memory::Memory;
+use taint::Shadow;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Comparison {
     LessThan = -1,
     EqualTo = 0,
     GreaterThan = 1,
 }
 
+impl From<Ordering> for Comparison {
+    fn from(ordering: Ordering) -> Self {
+        match ordering {
+            Ordering::Less => Comparison::LessThan,
+            Ordering::Equal => Comparison::EqualTo,
+            Ordering::Greater => Comparison::GreaterThan,
+        }
+    }
+}
+
+impl From<Comparison> for Ordering {
+    fn from(cmp: Comparison) -> Self {
+        match cmp {
+            Comparison::LessThan => Ordering::Less,
+            Comparison::EqualTo => Ordering::Equal,
+            Comparison::GreaterThan => Ordering::Greater,
+        }
+    }
+}
+
+// where execution goes after an instruction
+enum Next {
+    Continue,
+    Jump(u64),
+    Halt,
+}
+
+// the next instruction and its raw word, or why it could not be fetched
+type Fetch = Result<Option<(Option<Word>, Instruction)>, (StopReason, Option<Word>)>;
+
+// the memory words an instruction reads and writes when it runs
+struct Accesses {
+    reads: Range<u64>,
+    writes: Range<u64>,
+}
+
+impl Accesses {
+    fn len(&self) -> u64 {
+        self.reads.end - self.reads.start + self.writes.end - self.writes.start
+    }
+
+    fn addrs(&self) -> impl Iterator<Item = u64> {
+        self.reads.clone().chain(self.writes.clone())
+    }
+}
+
+/// A MIX machine.
+///
+/// `MMix` owns all of its state and is `Send` and `Sync`: a machine can be
+/// moved to a worker thread, and shared read-only between threads while
+/// nobody runs it. Running needs `&mut self`, so one machine runs on one
+/// thread at a time. To stop a run from another thread, pass an
+/// `AtomicBool` to `RunConfig::cancel_token` or `execute_cancellable`.
+/// `clone_state` forks a machine cheaply for parallel runs, since each page
+/// of memory is shared until written. `RunConfig` holds arbitrary callbacks and is
+/// therefore neither `Send` nor `Sync`.
+#[derive(Clone)]
 pub struct MMix {
-    a: i64,
-    x: i64,
+    a: Word,
+    x: Word,
     i: Vec<i64>,
     j: u64,
     overflow: bool,
-    cmp: Comparison,
-    memory: Vec<i64>,
+    cmp: Option<Comparison>,
+    memory: Memory,
+    devices: Vec<Box<dyn MixDevice>>,
+    // the cycle at which each unit finishes its last operation
+    busy_until: Vec<u64>,
+    config: MachineConfig,
+    pc: usize,
+    arith_events: Vec<ArithEvent>,
+    clobber_events: Vec<ClobberEvent>,
+    cycles: u64,
+    mems: u64,
+    opcode_stats: OpcodeStats,
+    loaded_code: Vec<Range<usize>>,
+    entry_point: Option<u64>,
+    // the assertions of loaded programs, by the address of their HLT
+    assertions: BTreeMap<u64, Assertion>,
+    shadow: Option<Shadow>,
+    // the I/O of the run in progress
+    io: Vec<IoEvent>,
+}
+
+impl Default for MMix {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MMix {
     pub fn new() -> Self {
+        Self::with_config(MachineConfig::default())
+    }
+
+    pub fn with_config(config: MachineConfig) -> Self {
         Self {
-            a: 0,
-            x: 0,
-            i: vec![0; 10],
+            a: Word::default(),
+            x: Word::default(),
+            // indexed by register number, so i[0] is never used
+            i: vec![0; config.index_registers + 1],
             j: 0,
             overflow: false,
-            cmp: Comparison::EqualTo,
-            memory: vec![0; 4000],
+            cmp: None,
+            memory: Memory::new(config.memory_size),
+            devices: (0..UNITS).map(device::default_device).collect(),
+            busy_until: vec![0; UNITS as usize],
+            shadow: config
+                .taint_tracking
+                .then(|| Shadow::new(config.memory_size, config.index_registers)),
+            config,
+            pc: 0,
+            arith_events: Vec::new(),
+            clobber_events: Vec::new(),
+            cycles: 0,
+            mems: 0,
+            opcode_stats: OpcodeStats::new(),
+            loaded_code: Vec::new(),
+            entry_point: None,
+            assertions: BTreeMap::new(),
+            io: Vec::new(),
         }
     }
 
-    pub fn execute(&mut self, program: &Program) {
-        let mut pc = 0;
-        while pc < program.instructions.len() {
-            let instruction = &program.instructions[pc];
-            match instruction {
-                Instruction::ADD(addr) => {
-                    let value = self.memory[*addr as usize];
-                    let (result, overflow) = self.a.overflowing_add(value);
-                    self.a = result;
-                    self.overflow = overflow;
-                }
-                Instruction::SUB(addr) => {
-                    let value = self.memory[*addr as usize];
-                    let (result, overflow) = self.a.overflowing_sub(value);
-                    self.a = result;
-                    self.overflow = overflow;
-                }
-                Instruction::STA(addr) => {
-                    self.memory[*addr as usize] = self.a;
-                }
-                Instruction::STX(addr) => {
-                    self.memory[*addr as usize] = self.x;
-                }
-                Instruction::STI(n, addr) => {
-                    self.memory[*addr as usize] = self.i[*n as usize];
-                }
-                Instruction::STJ(addr) => {
-                    self.memory[*addr as usize] = self.j as i64;
-                }
-                Instruction::STZ(addr) => {
-                    self.memory[*addr as usize] = 0;
-                }
-                Instruction::ENTA(value) => {
-                    self.a = *value;
-                }
-                Instruction::ENTX(value) => {
-                    self.x = *value;
-                }
-                Instruction::ENTI(n, value) => {
-                    self.i[*n as usize] = *value;
-                }
-                Instruction::ENNA(value) => {
-                    self.a = -*value;
-                }
-                Instruction::ENNX(value) => {
-                    self.x = -*value;
-                }
-                Instruction::ENNI(n, value) => {
-                    self.i[*n as usize] = -*value;
-                }
-                Instruction::LDA(addr) => {
-                    self.a = self.memory[*addr as usize];
-                }
-                Instruction::LDX(addr) => {
-                    self.x = self.memory[*addr as usize];
-                }
-                Instruction::LDI(n, addr) => {
-                    self.i[*n as usize] = self.memory[*addr as usize];
-                }
-                Instruction::LDAN(addr) => {
-                    self.a = -self.memory[*addr as usize];
-                }
-                Instruction::LDXN(addr) => {
-                    self.x = -self.memory[*addr as usize];
-                }
-                Instruction::LDIN(n, addr) => {
-                    self.i[*n as usize] = -self.memory[*addr as usize];
-                }
-            }
-            pc += 1;
-        }
+    /// Fork the machine. Memory is shared a page of 64 words at a time, and
+    /// a write copies only its own page.
+    pub fn clone_state(&self) -> Self {
+        self.clone()
     }
-}
 
-#[derive(Debug, PartialEq)]
-pub enum Instruction {
-    LDA(u64),
-    LDX(u64),
-    LDI(u8, u64),
-    LDAN(u64),
-    LDXN(u64),
-    LDIN(u8, u64),
-    STA(u64),
-    STX(u64),
-    STI(u8, u64),
-    STJ(u64),
-    STZ(u64),
-    ENTA(i64),
-    ENTX(i64),
-    ENTI(u8, i64),
-    ENNA(i64),
-    ENNX(i64),
-    ENNI(u8, i64),
-    ADD(u64),
-    SUB(u64),
-}
+    /// Whether no page of memory has been copied since the fork.
+    pub fn shares_memory_with(&self, other: &MMix) -> bool {
+        self.memory.is_shared_with(&other.memory)
+    }
 
-const MAX_INSTRUCTION_LENGTH: usize = 4;
+    pub fn config(&self) -> &MachineConfig {
+        &self.config
+    }
 
-pub struct Program {
-    scanner: Scanner,
-    instructions: Vec<Instruction>,
-    line: usize,
-}
+    /// Replace the device on `unit`.
+    pub fn attach(&mut self, unit: u8, device: impl MixDevice) {
+        assert!(unit < UNITS, "Invalid unit {}", unit);
+        self.devices[unit as usize] = Box::new(device);
+    }
 
-impl Program {
-    pub fn new(input: &str) -> Self {
-        Self {
-            scanner: Scanner::new(input),
-            instructions: Vec::new(),
-            line: 0,
-        }
+    /// The device on `unit`, if it is a `T`.
+    pub fn device<T: MixDevice>(&self, unit: u8) -> Option<&T> {
+        device::downcast(self.devices.get(unit as usize)?.as_ref())
     }
 
-    pub fn parse(&mut self) {
-        while let Some(instruction) = self.parse_instruction() {
-            match instruction.as_str() {
-                "ADD" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::ADD(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "SUB" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::SUB(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "STA" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::STA(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "STX" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::STX(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ST1" | "ST2" | "ST3" | "ST4" | "ST5" | "ST6" | "ST7" | "ST8" | "ST9" | "ST10" => {
-                    let n = instruction.chars().nth(2).unwrap().to_digit(10).unwrap() as u8;
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::STI(n, value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "STJ" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::STJ(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "STZ" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::STZ(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ENTA" => {
-                    if let Some(value) = self.parse_value() {
-                        self.instructions.push(Instruction::ENTA(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ENTX" => {
-                    if let Some(value) = self.parse_value() {
-                        self.instructions.push(Instruction::ENTX(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ENT1" | "ENT2" | "ENT3" | "ENT4" | "ENT5" | "ENT6" | "ENT7" | "ENT8" | "ENT9"
-                | "ENT10" => {
-                    let n = instruction.chars().nth(3).unwrap().to_digit(10).unwrap() as u8;
-                    if let Some(value) = self.parse_value() {
-                        self.instructions.push(Instruction::ENTI(n, value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ENNA" => {
-                    if let Some(value) = self.parse_value() {
-                        self.instructions.push(Instruction::ENNA(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ENNX" => {
-                    if let Some(value) = self.parse_value() {
-                        self.instructions.push(Instruction::ENNX(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "ENN1" | "ENN2" | "ENN3" | "ENN4" | "ENN5" | "ENN6" | "ENN7" | "ENN8" | "ENN9"
-                | "ENN10" => {
-                    let n = instruction.chars().nth(3).unwrap().to_digit(10).unwrap() as u8;
-                    if let Some(value) = self.parse_value() {
-                        self.instructions.push(Instruction::ENNI(n, value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "LDA" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::LDA(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "LDX" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::LDX(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "LD1" | "LD2" | "LD3" | "LD4" | "LD5" | "LD6" | "LD7" | "LD8" | "LD9" | "LD10" => {
-                    let n = instruction.chars().nth(2).unwrap().to_digit(10).unwrap() as u8;
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::LDI(n, value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "LDAN" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::LDAN(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "LDXN" => {
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::LDXN(value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                "LD1N" | "LD2N" | "LD3N" | "LD4N" | "LD5N" | "LD6N" | "LD7N" | "LD8N" | "LD9N" => {
-                    let n = instruction.chars().nth(2).unwrap().to_digit(10).unwrap() as u8;
-                    if let Some(value) = self.parse_address() {
-                        self.instructions.push(Instruction::LDIN(n, value));
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-                _ => panic!("Unknown instruction at line {}", self.line),
-            }
-        }
+    pub fn device_mut<T: MixDevice>(&mut self, unit: u8) -> Option<&mut T> {
+        device::downcast_mut(self.devices.get_mut(unit as usize)?.as_mut())
     }
 
-    pub fn parse_instruction(&mut self) -> Option<String> {
-        let mut instruction = String::new();
-        while !self.scanner.is_done() {
-            let ch = self.scanner.pop();
-            if ch.is_none() {
-                break;
-            }
-            let c = ch.unwrap();
-            match c {
-                ' ' => break,
-                '\n' => {
-                    self.line += 1;
-                    break;
-                }
-                '\t' => break,
-                '\r' => break,
-                _ => {
-                    if c.is_ascii_uppercase() || (instruction.len() >= 2 && c.is_ascii_digit()) {
-                        instruction.push(*c)
-                    } else {
-                        panic!("Invalid instruction at line {}", self.line)
-                    }
-                }
-            }
-        }
-        if instruction.is_empty() {
-            return None;
+    pub fn load_deck(&mut self, deck: &CardDeck) {
+        self.device_mut::<CardReader>(CARD_READER)
+            .expect("Unit 16 is not a card reader")
+            .load(deck);
+    }
+
+    pub fn read_card(&mut self, addr: u64) -> Result<bool, AddressError> {
+        self.input(CARD_READER, addr)
+    }
+
+    pub fn punch_card(&mut self, addr: u64) -> Result<(), AddressError> {
+        self.output(CARD_PUNCH, addr)
+    }
+
+    pub fn punched_deck(&self) -> &CardDeck {
+        self.device::<CardPunch>(CARD_PUNCH)
+            .expect("Unit 17 is not a card punch")
+            .deck()
+    }
+
+    /// IN: read the next block from `unit` into memory at `addr`. False
+    /// if the unit has no more input.
+    pub fn input(&mut self, unit: u8, addr: u64) -> Result<bool, AddressError> {
+        self.range(addr, self.block_size(unit)?)?;
+        Ok(self.transfer_in(unit, addr))
+    }
+
+    /// OUT: write the block at `addr` to `unit`.
+    pub fn output(&mut self, unit: u8, addr: u64) -> Result<(), AddressError> {
+        self.range(addr, self.block_size(unit)?)?;
+        self.transfer_out(unit, addr);
+        Ok(())
+    }
+
+    fn block_size(&self, unit: u8) -> Result<usize, AddressError> {
+        self.devices
+            .get(unit as usize)
+            .map(|device| device.block_size())
+            .ok_or(AddressError::InvalidUnit(unit))
+    }
+
+    fn transfer_in(&mut self, unit: u8, addr: u64) -> bool {
+        let rx = self.value(self.x);
+        let device = &mut self.devices[unit as usize];
+        let mut block = vec![Word::default(); device.block_size()];
+        if !device.read(&mut block, rx) {
+            return false;
         }
-        if instruction.len() > MAX_INSTRUCTION_LENGTH {
-            panic!("Invalid instruction at line {}", self.line)
+        self.memory.write(addr as usize, &block);
+        if let Some(shadow) = &mut self.shadow {
+            shadow.input(addr, block.len());
         }
-        Some(instruction)
+        true
     }
 
-    fn parse_address(&mut self) -> Option<u64> {
-        let value = self.parse_digit_string();
-        if let Some(value) = value {
-            return Some(value.parse().unwrap());
-        }
-        None
+    fn transfer_out(&mut self, unit: u8, addr: u64) {
+        let rx = self.value(self.x);
+        let device = &mut self.devices[unit as usize];
+        let addr = addr as usize;
+        device.write(&self.memory.read(addr, device.block_size()), rx);
     }
 
-    fn parse_value(&mut self) -> Option<i64> {
-        let ch = self.scanner.peek();
-        if ch.is_none() {
-            return None;
-        }
-        let c = ch.unwrap();
-        let mut sign = 1;
-        if *c == '-' {
-            self.scanner.pop();
-            sign = -1;
+    pub fn execute(&mut self, program: &Program) -> RunSummary {
+        self.execute_with(program, &mut RunConfig::default())
+    }
+
+    pub fn execute_with(&mut self, program: &Program, config: &mut RunConfig) -> RunSummary {
+        self.pc = program.entry_point.unwrap_or(0) as usize;
+        let byte_size = self.config.byte_size;
+        let summary = self.run(config, false, |mmix| {
+            let instruction = program.instructions.get(mmix.pc).cloned();
+            Ok(instruction.map(|instruction| (instruction.to_word(byte_size), instruction)))
+        });
+        let assertion = program.assertions.get(&(summary.pc as u64));
+        summary.assertion(assertion)
+    }
+
+    pub fn execute_cancellable(&mut self, program: &Program, token: &AtomicBool) -> RunSummary {
+        self.execute_with(program, &mut RunConfig::new().cancel_token(token))
+    }
+
+    // the memory indices of `len` words from `addr`, if they all exist
+    fn range(&self, addr: u64, len: usize) -> Result<Range<usize>, AddressError> {
+        usize::try_from(addr)
+            .ok()
+            .and_then(|start| Some(start..start.checked_add(len)?))
+            .filter(|range| range.end <= self.memory.len())
+            .ok_or(AddressError::OutsideMemory { addr, len })
+    }
+
+    pub fn write_memory(&mut self, addr: u64, words: &[Word]) -> Result<(), AddressError> {
+        let range = self.range(addr, words.len())?;
+        self.memory.write(range.start, words);
+        if let Some(shadow) = &mut self.shadow {
+            shadow.input(addr, words.len());
         }
-        let value = self.parse_digit_string();
-        if let Some(value) = value {
-            let value = value.parse::<i64>().unwrap();
-            return Some(sign * value);
+        Ok(())
+    }
+
+    pub fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<Word>, AddressError> {
+        let range = self.range(addr, len)?;
+        Ok(self.memory.read(range.start, range.len()))
+    }
+
+    /// Store `text` as MIX characters, five to a word, and return the
+    /// number of words written.
+    pub fn write_text(&mut self, addr: u64, text: &str) -> Result<usize, AddressError> {
+        let words = text_to_words(text)
+            .unwrap_or_else(|| panic!("Text {:?} has no MIX character code", text));
+        self.write_memory(addr, &words)?;
+        Ok(words.len())
+    }
+
+    /// Read `len` words of MIX characters as text.
+    pub fn read_text(&self, addr: u64, len: usize) -> Result<String, AddressError> {
+        Ok(words_to_text(&self.read_memory(addr, len)?))
+    }
+
+    /// The word at `addr`, or None outside memory. Never changes the
+    /// machine state.
+    pub fn peek_word(&self, addr: u64) -> Option<Word> {
+        self.memory.get(addr as usize)
+    }
+
+    /// Decode the word at `addr` without moving the pc, or None if it is
+    /// outside memory or not an instruction.
+    pub fn peek_instruction(&self, addr: u64) -> Option<Instruction> {
+        Instruction::from_word(self.peek_word(addr)?, self.config.byte_size)
+    }
+
+    pub fn fill(&mut self, addr: u64, len: usize, value: Word) -> Result<(), AddressError> {
+        self.write_memory(addr, &vec![value; len])
+    }
+
+    /// Copy `len` words from `src` to `dst` in one step, as if through a
+    /// buffer, so overlapping ranges copy the original words.
+    pub fn copy(&mut self, dst: u64, src: u64, len: usize) -> Result<(), AddressError> {
+        self.range(src, len)?;
+        self.range(dst, len)?;
+        self.copy_words(dst, src, len);
+        Ok(())
+    }
+
+    fn copy_words(&mut self, dst: u64, src: u64, len: usize) {
+        let src = src as usize;
+        let words = self.memory.read(src, len);
+        self.memory.write(dst as usize, &words);
+        if let Some(shadow) = &mut self.shadow {
+            shadow.copy(dst, src as u64, len);
         }
-        None
     }
 
-    fn parse_digit_string(&mut self) -> Option<String> {
-        let mut value = String::new();
-        while !self.scanner.is_done() {
-            let ch = self.scanner.pop();
-            if ch.is_none() {
-                break;
-            }
-            let c = ch.unwrap();
-            match c {
-                ' ' => break,
-                '\n' => {
-                    self.line += 1;
-                    break;
-                }
-                '\t' => break,
-                '\r' => break,
-                _ => {
-                    if c.is_ascii_digit() {
-                        value.push(*c)
-                    } else if value.is_empty() {
-                        break;
-                    } else {
-                        panic!("Invalid value at line {}", self.line)
-                    }
-                }
-            }
+    /// Assemble `program` at `origin` and load it as code, with its
+    /// assertions. The entry point becomes the address named by its `END`,
+    /// if it has one.
+    pub fn load_program_into_memory(
+        &mut self,
+        program: &Program,
+        origin: u64,
+    ) -> Result<(), AddressError> {
+        self.load_code(origin, &program.assemble_at(origin))?;
+        for (pc, assertion) in &program.assertions {
+            self.assertions.insert(origin + pc, assertion.clone());
         }
-        if value.is_empty() {
-            return None;
+        if let Some(entry) = program.entry_point {
+            self.entry_point = Some(origin + entry);
         }
-        Some(value)
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Store already assembled instruction words at `origin` and mark them
+    /// as loaded code.
+    pub fn load_code(&mut self, origin: u64, words: &[Word]) -> Result<(), AddressError> {
+        let range = self.range(origin, words.len())?;
+        self.memory.write(range.start, words);
+        self.assertions
+            .retain(|&addr, _| !range.contains(&(addr as usize)));
+        self.loaded_code.push(range);
+        if self.entry_point.is_none() {
+            self.entry_point = Some(origin);
+        }
+        Ok(())
+    }
 
-    #[test]
-    fn test_parse_instruction_add() {
-        let mut program = Program::new("ADD 100\n");
-        assert_eq!(program.parse_instruction(), Some("ADD".to_string()));
+    pub fn entry_point(&self) -> Option<u64> {
+        self.entry_point
     }
 
-    #[test]
-    fn test_parse_instruction_sub() {
-        let mut program = Program::new("SUB 100\n");
-        assert_eq!(program.parse_instruction(), Some("SUB".to_string()));
+    /// Start `execute_loaded` at `addr`, which must be in memory and, if
+    /// the machine checks loaded code, inside it.
+    pub fn set_entry_point(&mut self, addr: u64) -> Result<(), AddressError> {
+        self.check_code_address(addr)?;
+        self.entry_point = Some(addr);
+        Ok(())
     }
 
-    #[test]
-    fn test_parse_instruction_lda() {
-        let mut program = Program::new("LDA 100\n");
-        assert_eq!(program.parse_instruction(), Some("LDA".to_string()));
+    fn check_code_address(&self, addr: u64) -> Result<(), AddressError> {
+        if addr >= self.memory.len() as u64 {
+            return Err(AddressError::OutsideMemory { addr, len: 1 });
+        }
+        if self.config.check_loaded_code && !self.is_loaded_code(addr) {
+            return Err(AddressError::OutsideLoadedCode(addr));
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_parse_instruction_ldx() {
-        let mut program = Program::new("LDX 100\n");
-        assert_eq!(program.parse_instruction(), Some("LDX".to_string()));
+    pub fn is_loaded_code(&self, addr: u64) -> bool {
+        let addr = addr as usize;
+        self.loaded_code.iter().any(|range| range.contains(&addr))
     }
 
-    #[test]
-    fn test_parse_instruction_ldi() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("LD{} 100\n", i).as_str());
-            assert_eq!(program.parse_instruction(), Some(format!("LD{}", i)));
+    /// Run from the entry point. A machine with nothing loaded stops at
+    /// once with `StopReason::NothingLoaded`.
+    pub fn execute_loaded(&mut self) -> RunSummary {
+        match self.entry_point {
+            Some(start) => self.execute_from_memory(start),
+            None => RunSummary::new(0, Duration::ZERO, StopReason::NothingLoaded).at(
+                self.pc,
+                None,
+                None,
+                self.register_summary(),
+            ),
         }
     }
 
-    #[test]
-    fn test_parse_instruction_ldan() {
-        let mut program = Program::new("LDAN 100\n");
-        assert_eq!(program.parse_instruction(), Some("LDAN".to_string()));
+    pub fn execute_from_memory(&mut self, start: u64) -> RunSummary {
+        self.execute_from_memory_with(start, &mut RunConfig::default())
     }
 
-    #[test]
-    fn test_parse_instruction_ldxn() {
-        let mut program = Program::new("LDXN 100\n");
-        assert_eq!(program.parse_instruction(), Some("LDXN".to_string()));
+    pub fn execute_from_memory_with(&mut self, start: u64, config: &mut RunConfig) -> RunSummary {
+        self.pc = start as usize;
+        let summary = self.run(config, true, MMix::fetch_memory);
+        let assertion = self.assertions.get(&(summary.pc as u64));
+        summary.assertion(assertion)
     }
 
-    #[test]
+    /// Execute the single instruction in memory at the pc.
+    pub fn step(&mut self) -> RunSummary {
+        // a jump to itself leaves the pc where it was, so count fetches
+        // rather than compare the pc
+        let fetched = Cell::new(false);
+        self.run(&mut RunConfig::new(), true, |mmix| {
+            if fetched.replace(true) {
+                Ok(None)
+            } else {
+                mmix.fetch_memory()
+            }
+        })
+    }
+
+    pub fn pc(&self) -> u64 {
+        self.pc as u64
+    }
+
+    /// Move the pc to `addr`, which must be in memory and, if the machine
+    /// checks loaded code, inside it.
+    pub fn set_pc(&mut self, addr: u64) -> Result<(), AddressError> {
+        self.check_code_address(addr)?;
+        self.pc = addr as usize;
+        Ok(())
+    }
+
+    /// The value of `reg`; -0 reads as 0.
+    pub fn register(&self, reg: RegisterName) -> i64 {
+        match reg {
+            RegisterName::A => self.value(self.a),
+            RegisterName::X => self.value(self.x),
+            RegisterName::J => self.j as i64,
+            RegisterName::I(n) => self.i[n.index()],
+        }
+    }
+
+    /// The contents of `reg` as a word, keeping the sign of rA and rX.
+    pub fn register_word(&self, reg: RegisterName) -> Word {
+        match reg {
+            RegisterName::A => self.a,
+            RegisterName::X => self.x,
+            _ => self.word(self.register(reg)),
+        }
+    }
+
+    // a word's value at this machine's byte size
+    pub(crate) fn value(&self, word: Word) -> i64 {
+        word.value(self.config.byte_size)
+    }
+
+    fn word(&self, value: i64) -> Word {
+        Word::from_value(value, self.config.byte_size)
+    }
+
+    fn fetch_memory(&self) -> Fetch {
+        let word = match self.peek_word(self.pc as u64) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        if !self.is_loaded_code(self.pc as u64) {
+            if self.config.check_loaded_code {
+                return Err((StopReason::OutsideLoadedCode, Some(word)));
+            }
+            // +0 is a NOP, but outside loaded code it is memory nobody
+            // wrote
+            if word == Word::default() {
+                return Err((StopReason::UninitializedMemory, Some(word)));
+            }
+        }
+        match Instruction::from_word(word, self.config.byte_size) {
+            Some(instruction) => Ok(Some((Some(word), instruction))),
+            None => Err((StopReason::InvalidInstruction, Some(word))),
+        }
+    }
+
+    fn run(
+        &mut self,
+        config: &mut RunConfig,
+        from_memory: bool,
+        fetch: impl Fn(&MMix) -> Fetch,
+    ) -> RunSummary {
+        let start = Instant::now();
+        let mut count = 0;
+        let first_cycle = self.cycles;
+        let first_mem = self.mems;
+        let mut energy = 0;
+        let (stop_reason, word, instruction) = loop {
+            let (word, instruction) = match fetch(self) {
+                Ok(Some(fetched)) => fetched,
+                Ok(None) => break (StopReason::EndOfProgram, None, None),
+                Err((reason, word)) => break (reason, word, None),
+            };
+            if config.is_cancelled() {
+                break (StopReason::Cancelled, word, Some(instruction));
+            }
+            if instruction.is_extended() && self.config.instruction_set == InstructionSet::Strict {
+                break (StopReason::ExtendedInstruction, word, Some(instruction));
+            }
+            if !self.has_index_registers(&instruction)
+                || (instruction.is_binary() && !self.config.byte_size.is_power_of_two())
+            {
+                break (StopReason::InvalidInstruction, word, Some(instruction));
+            }
+            let Some((effective, accesses)) = self.effective(&instruction) else {
+                break (StopReason::InvalidAddress, word, Some(instruction));
+            };
+            if from_memory && self.config.detect_self_clobber {
+                self.check_clobber(&accesses);
+            }
+            count += 1;
+            self.cycles += instruction.time();
+            self.opcode_stats.record(&instruction);
+            self.mems += accesses.len();
+            energy += config.charge(&effective, accesses.addrs());
+            let before = config.is_tracing().then(|| self.trace_snapshot(&accesses));
+            if let Some(shadow) = &mut self.shadow {
+                shadow.propagate(&instruction, &effective);
+            }
+            let next = self.execute_instruction(&effective);
+            if let Some(before) = before {
+                self.trace(config, word, instruction.clone(), effective, before);
+            }
+            match next {
+                Next::Continue => self.pc += 1,
+                Next::Jump(addr) => self.pc = addr as usize,
+                Next::Halt => break (StopReason::Halted, word, Some(instruction)),
+            }
+            if config.tick(self, count) == ControlFlow::Break(()) {
+                break (StopReason::Stopped, None, None);
+            }
+        };
+        RunSummary::new(count, start.elapsed(), stop_reason)
+            .cycles(self.cycles - first_cycle)
+            .mems(self.mems - first_mem)
+            .energy(energy)
+            .io(std::mem::take(&mut self.io))
+            .at(self.pc, word, instruction, self.register_summary())
+    }
+
+    /// Assemble one line, patch it into memory at the current pc and
+    /// execute it, for interactive use. Statements separated by `;` run
+    /// in turn, each at the pc the one before left, until one of them
+    /// stops the machine. Returns the summary of the last one run, or
+    /// None for empty and comment lines.
+    pub fn asm_line(&mut self, line: &str) -> Option<RunSummary> {
+        let mut program = Program::with_config(line, &self.config);
+        program.parse();
+        let mut summary = None;
+        for instruction in program.instructions() {
+            let word = instruction.to_word(self.config.byte_size);
+            if let Some(word) = word.filter(|_| self.pc < self.memory.len()) {
+                self.memory.set(self.pc, word);
+            }
+            // the patched word runs once, even if it jumps to itself
+            let fetched = Cell::new(false);
+            let run = self.run(&mut RunConfig::new(), false, |_| {
+                Ok((!fetched.replace(true)).then(|| (word, instruction.clone())))
+            });
+            let stopped = run.stop_reason != StopReason::EndOfProgram;
+            summary = Some(run);
+            if stopped {
+                break;
+            }
+        }
+        summary
+    }
+
+    /// Hash of the registers, indicators, pc and memory. Equal machines
+    /// have equal digests, and stores update the memory part in constant
+    /// time, so runs can be compared cheaply at intervals.
+    pub fn state_digest(&self) -> u64 {
+        let cmp = match self.cmp {
+            None => 0,
+            Some(Comparison::LessThan) => 1,
+            Some(Comparison::EqualTo) => 2,
+            Some(Comparison::GreaterThan) => 3,
+        };
+        let registers = [self.a.bits(), self.x.bits(), self.j, self.pc as u64]
+            .into_iter()
+            .chain(self.i.iter().map(|&value| value as u64))
+            .chain([self.overflow as u64, cmp]);
+        registers.fold(self.memory.digest(), |digest, value| {
+            memory::mix(digest ^ value)
+        })
+    }
+
+    /// The registers and indicators, which display as a one-line summary.
+    pub fn register_summary(&self) -> Registers {
+        let index_registers = match self.config.instruction_set {
+            InstructionSet::Strict => STRICT_INDEX_REGISTERS,
+            InstructionSet::Extended => MAX_INDEX_REGISTER,
+        } as usize;
+        let count = index_registers.min(self.i.len().saturating_sub(1));
+        Registers {
+            a: self.value(self.a),
+            x: self.value(self.x),
+            i: self.i[1..=count].to_vec(),
+            j: self.j,
+            overflow: self.overflow,
+            comparison: self.comparison_indicator(),
+        }
+    }
+
+    /// The comparison indicator, or None before any comparison was made.
+    pub fn comparison_indicator(&self) -> Option<Ordering> {
+        self.cmp.map(Ordering::from)
+    }
+
+    /// Input words that influenced `reg`, if taint tracking is enabled.
+    pub fn register_origins(&self, reg: RegisterName) -> Option<Origins> {
+        self.shadow.as_ref().map(|shadow| shadow.register(&reg))
+    }
+
+    /// Input words that influenced the word at `addr`, if taint tracking
+    /// is enabled.
+    pub fn memory_origins(&self, addr: u64) -> Option<Origins> {
+        self.shadow.as_ref().map(|shadow| shadow.memory(addr))
+    }
+
+    pub fn arith_events(&self) -> &[ArithEvent] {
+        &self.arith_events
+    }
+
+    pub fn clear_arith_events(&mut self) {
+        self.arith_events.clear();
+    }
+
+    /// MIX time units charged since the machine was created. Unlike the
+    /// instruction count in a `RunSummary` this keeps growing across runs.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Memory words read or written since the machine was created, the
+    /// "mems" of TAOCP's running-time comparisons.
+    pub fn mems(&self) -> u64 {
+        self.mems
+    }
+
+    /// Instructions executed since the machine was created, by family.
+    pub fn opcode_stats(&self) -> &OpcodeStats {
+        &self.opcode_stats
+    }
+
+    pub fn clobber_events(&self) -> &[ClobberEvent] {
+        &self.clobber_events
+    }
+
+    pub fn clear_clobber_events(&mut self) {
+        self.clobber_events.clear();
+    }
+
+    fn traced_registers(&self) -> Vec<RegisterName> {
+        let index = (1..self.i.len()).filter_map(|n| IndexReg::try_from(n as u8).ok());
+        [RegisterName::A, RegisterName::X]
+            .into_iter()
+            .chain(index.map(RegisterName::I))
+            .chain([RegisterName::J])
+            .collect()
+    }
+
+    // register values and the words an instruction writes, before it runs
+    fn trace_snapshot(&self, accesses: &Accesses) -> (Vec<i64>, Vec<(u64, Word)>) {
+        let registers = self
+            .traced_registers()
+            .into_iter()
+            .map(|reg| self.register(reg))
+            .collect();
+        let written = accesses
+            .writes
+            .clone()
+            .map(|addr| (addr, self.memory[addr as usize]))
+            .collect();
+        (registers, written)
+    }
+
+    fn trace(
+        &self,
+        config: &mut RunConfig,
+        word: Option<Word>,
+        instruction: Instruction,
+        effective: Instruction,
+        (registers, written): (Vec<i64>, Vec<(u64, Word)>),
+    ) {
+        config.trace(TraceEvent::Execute {
+            pc: self.pc,
+            word,
+            instruction,
+            address: effective.address(),
+            effective,
+        });
+        for (reg, old) in self.traced_registers().into_iter().zip(registers) {
+            let new = self.register(reg);
+            if new != old {
+                config.trace(TraceEvent::Register { reg, old, new });
+            }
+        }
+        for (addr, old) in written {
+            let new = self.memory[addr as usize];
+            if new != old {
+                config.trace(TraceEvent::Memory { addr, old, new });
+            }
+        }
+    }
+
+    // whether the machine has every index register the instruction uses
+    fn has_index_registers(&self, instruction: &Instruction) -> bool {
+        let has = |n: IndexReg| n.index() < self.i.len();
+        match instruction {
+            Instruction::Indexed(n, instruction) => {
+                has(*n) && self.has_index_registers(instruction)
+            }
+            _ => instruction.index_reg().is_none_or(has),
+        }
+    }
+
+    // the instruction with its index register applied and the words it
+    // will access, or None if its address is negative or any of those
+    // words is outside memory
+    fn effective(&self, instruction: &Instruction) -> Option<(Instruction, Accesses)> {
+        let effective = match instruction {
+            Instruction::Indexed(n, instruction) => {
+                instruction.offset_address(self.i[n.index()])?
+            }
+            _ => instruction.clone(),
+        };
+        let accesses = self.accesses(&effective)?;
+        Some((effective, accesses))
+    }
+
+    fn accesses(&self, instruction: &Instruction) -> Option<Accesses> {
+        let block = |addr: u64, len: usize| addr..addr.saturating_add(len as u64);
+        let (reads, writes) = match instruction {
+            Instruction::IN(addr, unit) => (
+                0..0,
+                block(*addr, self.devices[*unit as usize].block_size()),
+            ),
+            Instruction::OUT(addr, unit) => (
+                block(*addr, self.devices[*unit as usize].block_size()),
+                0..0,
+            ),
+            Instruction::MOVE(addr, count) => {
+                // to rI1, which must not be negative if anything moves
+                if *count > 0 && self.i[1] < 0 {
+                    return None;
+                }
+                let dst = self.i[1].max(0) as u64;
+                (block(*addr, *count as usize), block(dst, *count as usize))
+            }
+            _ => match (instruction.store_address(), instruction.memory_operand()) {
+                (Some(addr), _) => (0..0, block(addr, 1)),
+                (None, Some(addr)) => (block(addr, 1), 0..0),
+                (None, None) => (0..0, 0..0),
+            },
+        };
+        let len = self.memory.len() as u64;
+        let in_memory = |range: &Range<u64>| range.is_empty() || range.end <= len;
+        (in_memory(&reads) && in_memory(&writes)).then_some(Accesses { reads, writes })
+    }
+
+    fn check_clobber(&mut self, accesses: &Accesses) {
+        let pc = self.pc as u64;
+        for addr in accesses.writes.clone() {
+            if addr == pc || addr == pc + 1 {
+                self.clobber_events.push(ClobberEvent { pc: self.pc, addr });
+            }
+        }
+    }
+
+    fn load(&self, addr: u64, field: Field) -> Word {
+        self.memory[addr as usize].field(field)
+    }
+
+    // an index register loads the value of the field
+    fn load_index(&self, addr: u64, field: Field) -> i64 {
+        self.value(self.load(addr, field))
+    }
+
+    fn store(&mut self, addr: u64, field: Field, source: Word) {
+        let addr = addr as usize;
+        let mut word = self.memory[addr];
+        word.set_field(field, source);
+        self.memory.set(addr, word);
+    }
+
+    // compare the same field of a register and a memory word, so that +0
+    // and -0 are equal
+    fn compare(&mut self, register: Word, addr: u64, field: Field) {
+        let value = self.value(self.load(addr, field));
+        self.cmp = Some(self.value(register.field(field)).cmp(&value).into());
+    }
+
+    // add the field of a memory word to rA, or subtract it
+    fn add(&mut self, addr: u64, field: Field, subtract: bool) {
+        let value = self.value(self.load(addr, field)) as i128;
+        self.a = self.sum(self.a, if subtract { -value } else { value });
+    }
+
+    // `register` plus `value`, for ADD, SUB, INC and DEC
+    fn sum(&mut self, register: Word, value: i128) -> Word {
+        let exact = self.value(register) as i128 + value;
+        let (mut result, overflow) = Word::wrap(exact, self.config.byte_size);
+        // a zero result keeps the sign of the register
+        if result.is_zero() {
+            result.negative = register.negative;
+        }
+        self.set_overflow(overflow);
+        result
+    }
+
+    // rA or rX set to ±`value`, with the sign of the instruction when the
+    // value is zero
+    fn enter(&self, value: i64, negate: bool) -> Word {
+        let mut word = self.word(value);
+        word.negative = (value < 0) != negate;
+        word
+    }
+
+    // shift rA, or rA and rX together, by `count` bytes
+    fn shift(&mut self, count: i64, with_x: bool, circular: bool) {
+        if with_x {
+            (self.a, self.x) = word::shift_pair(self.a, self.x, count, circular);
+        } else {
+            self.a = word::shift_pair(self.a, Word::default(), count, circular).0;
+        }
+    }
+
+    /// Whether `unit` is still working on its last IN, OUT or IOC, which
+    /// only happens with `MachineConfig::with_io_latency`.
+    pub fn is_busy(&self, unit: u8) -> bool {
+        self.busy_until
+            .get(unit as usize)
+            .is_some_and(|&until| self.cycles < until)
+    }
+
+    // wait until `unit` is ready, then keep it busy for its latency
+    // the words of the block on `unit` at `addr`
+    fn block(&self, unit: u8, addr: u64) -> Vec<Word> {
+        let addr = addr as usize;
+        self.memory
+            .read(addr, self.devices[unit as usize].block_size())
+    }
+
+    fn journal(&mut self, kind: IoKind, unit: u8, address: i64, words: Vec<Word>) {
+        self.io.push(IoEvent {
+            pc: self.pc,
+            unit,
+            kind,
+            address,
+            words,
+        });
+    }
+
+    fn start_io(&mut self, unit: u8) {
+        let latency = if self.config.io_latency {
+            self.config
+                .unit_latency(unit)
+                .unwrap_or_else(|| self.devices[unit as usize].latency())
+        } else {
+            0
+        };
+        let until = &mut self.busy_until[unit as usize];
+        self.cycles = self.cycles.max(*until);
+        *until = self.cycles + latency;
+    }
+
+    // shift the magnitudes of rA and rX together by `count` bits, keeping
+    // their signs
+    fn shift_binary(&mut self, count: i64) {
+        let size = self.config.byte_size as i64;
+        let limit = size.pow(WORD_BYTES as u32);
+        let bits = 2 * WORD_BYTES as u32 * self.config.byte_size.trailing_zeros();
+        let value = self.a.magnitude(self.config.byte_size) as u128 * limit as u128
+            + self.x.magnitude(self.config.byte_size) as u128;
+        let shifted = match count.unsigned_abs() {
+            n if n >= bits as u64 => 0,
+            n if count < 0 => value >> n,
+            n => (value << n) & ((1 << bits) - 1),
+        };
+        let mut a = self.word((shifted / limit as u128) as i64);
+        let mut x = self.word((shifted % limit as u128) as i64);
+        (a.negative, x.negative) = (self.a.negative, self.x.negative);
+        (self.a, self.x) = (a, x);
+    }
+
+    fn set_overflow(&mut self, overflow: bool) {
+        if self.config.arithmetic == Arithmetic::Relaxed {
+            self.overflow = overflow;
+        }
+        if overflow {
+            self.overflow = true;
+            self.arith_events.push(ArithEvent {
+                pc: self.pc,
+                kind: ArithEventKind::Overflow,
+            });
+        }
+    }
+
+    // a jump to `addr` if `condition` holds, saving the return in rJ
+    fn jump(&mut self, addr: u64, condition: bool) -> Next {
+        if !condition {
+            return Next::Continue;
+        }
+        self.j = self.pc as u64 + 1;
+        Next::Jump(addr)
+    }
+
+    fn compared(&self, comparisons: &[Comparison]) -> bool {
+        self.cmp.is_some_and(|cmp| comparisons.contains(&cmp))
+    }
+
+    fn execute_instruction(&mut self, instruction: &Instruction) -> Next {
+        match instruction {
+            Instruction::ADD(addr, field) => self.add(*addr, *field, false),
+            Instruction::SUB(addr, field) => self.add(*addr, *field, true),
+            Instruction::CMPA(addr, field) => self.compare(self.a, *addr, *field),
+            Instruction::CMPX(addr, field) => self.compare(self.x, *addr, *field),
+            Instruction::CMPI(n, addr, field) => {
+                self.compare(self.word(self.i[n.index()]), *addr, *field);
+            }
+            Instruction::STA(addr, field) => self.store(*addr, *field, self.a),
+            Instruction::STX(addr, field) => self.store(*addr, *field, self.x),
+            Instruction::STI(n, addr, field) => {
+                self.store(*addr, *field, self.word(self.i[n.index()]));
+            }
+            Instruction::STJ(addr, field) => self.store(*addr, *field, self.word(self.j as i64)),
+            Instruction::STZ(addr, field) => self.store(*addr, *field, Word::default()),
+            Instruction::ENTA(value) => {
+                self.a = self.enter(*value, false);
+            }
+            Instruction::ENTX(value) => {
+                self.x = self.enter(*value, false);
+            }
+            Instruction::ENTI(n, value) => {
+                self.i[n.index()] = *value;
+            }
+            Instruction::ENNA(value) => {
+                self.a = self.enter(*value, true);
+            }
+            Instruction::ENNX(value) => {
+                self.x = self.enter(*value, true);
+            }
+            Instruction::ENNI(n, value) => {
+                self.i[n.index()] = -*value;
+            }
+            Instruction::INCA(value) => self.a = self.sum(self.a, *value as i128),
+            Instruction::INCX(value) => self.x = self.sum(self.x, *value as i128),
+            Instruction::INCI(n, value) => self.i[n.index()] += *value,
+            Instruction::DECA(value) => self.a = self.sum(self.a, -(*value as i128)),
+            Instruction::DECX(value) => self.x = self.sum(self.x, -(*value as i128)),
+            Instruction::DECI(n, value) => self.i[n.index()] -= *value,
+            Instruction::LDA(addr, field) => {
+                self.a = self.load(*addr, *field);
+            }
+            Instruction::LDX(addr, field) => {
+                self.x = self.load(*addr, *field);
+            }
+            Instruction::LDI(n, addr, field) => {
+                self.i[n.index()] = self.load_index(*addr, *field);
+            }
+            Instruction::LDAN(addr, field) => {
+                self.a = self.load(*addr, *field).negated();
+            }
+            Instruction::LDXN(addr, field) => {
+                self.x = self.load(*addr, *field).negated();
+            }
+            Instruction::LDIN(n, addr, field) => {
+                self.i[n.index()] = -self.load_index(*addr, *field);
+            }
+            Instruction::IN(addr, unit) => {
+                self.start_io(*unit);
+                let words = if self.transfer_in(*unit, *addr) {
+                    self.block(*unit, *addr)
+                } else {
+                    Vec::new()
+                };
+                self.journal(IoKind::Input, *unit, *addr as i64, words);
+            }
+            Instruction::OUT(addr, unit) => {
+                self.start_io(*unit);
+                self.transfer_out(*unit, *addr);
+                let words = self.block(*unit, *addr);
+                self.journal(IoKind::Output, *unit, *addr as i64, words);
+            }
+            Instruction::IOC(m, unit) => {
+                self.start_io(*unit);
+                let rx = self.value(self.x);
+                self.devices[*unit as usize].control(*m, rx);
+                self.journal(IoKind::Control, *unit, *m, Vec::new());
+            }
+            Instruction::SLA(count) => self.shift(*count as i64, false, false),
+            Instruction::SRA(count) => self.shift(-(*count as i64), false, false),
+            Instruction::SLAX(count) => self.shift(*count as i64, true, false),
+            Instruction::SRAX(count) => self.shift(-(*count as i64), true, false),
+            Instruction::SLC(count) => self.shift(*count as i64, true, true),
+            Instruction::SRC(count) => self.shift(-(*count as i64), true, true),
+            Instruction::SLB(count) => self.shift_binary(*count as i64),
+            Instruction::SRB(count) => self.shift_binary(-(*count as i64)),
+            Instruction::MOVE(addr, count) => {
+                // to rI1, word by word, so an overlapping move repeats its
+                // source
+                let dst = self.i[1] as u64;
+                for k in 0..*count as u64 {
+                    self.copy_words(dst + k, addr + k, 1);
+                }
+                self.i[1] += *count as i64;
+            }
+            Instruction::JMP(addr) => return self.jump(*addr, true),
+            Instruction::JSJ(addr) => return Next::Jump(*addr),
+            Instruction::JOV(addr) => {
+                let overflow = std::mem::take(&mut self.overflow);
+                return self.jump(*addr, overflow);
+            }
+            Instruction::JNOV(addr) => {
+                let overflow = std::mem::take(&mut self.overflow);
+                return self.jump(*addr, !overflow);
+            }
+            Instruction::JL(addr) => {
+                return self.jump(*addr, self.compared(&[Comparison::LessThan]));
+            }
+            Instruction::JE(addr) => {
+                return self.jump(*addr, self.compared(&[Comparison::EqualTo]));
+            }
+            Instruction::JG(addr) => {
+                return self.jump(*addr, self.compared(&[Comparison::GreaterThan]));
+            }
+            Instruction::JGE(addr) => {
+                let condition = self.compared(&[Comparison::GreaterThan, Comparison::EqualTo]);
+                return self.jump(*addr, condition);
+            }
+            Instruction::JNE(addr) => {
+                let condition = self.compared(&[Comparison::LessThan, Comparison::GreaterThan]);
+                return self.jump(*addr, condition);
+            }
+            Instruction::JLE(addr) => {
+                let condition = self.compared(&[Comparison::LessThan, Comparison::EqualTo]);
+                return self.jump(*addr, condition);
+            }
+            Instruction::JA(test, addr) => {
+                return self.jump(*addr, test.holds(self.value(self.a)));
+            }
+            Instruction::JX(test, addr) => {
+                return self.jump(*addr, test.holds(self.value(self.x)));
+            }
+            Instruction::JAE(addr) => return self.jump(*addr, self.value(self.a) % 2 == 0),
+            Instruction::JAO(addr) => return self.jump(*addr, self.value(self.a) % 2 != 0),
+            Instruction::JXE(addr) => return self.jump(*addr, self.value(self.x) % 2 == 0),
+            Instruction::JXO(addr) => return self.jump(*addr, self.value(self.x) % 2 != 0),
+            Instruction::JI(n, test, addr) => {
+                return self.jump(*addr, test.holds(self.i[n.index()]));
+            }
+            Instruction::JBUS(addr, unit) => return self.jump(*addr, self.is_busy(*unit)),
+            Instruction::JRED(addr, unit) => return self.jump(*addr, !self.is_busy(*unit)),
+            Instruction::HLT => return Next::Halt,
+            Instruction::NOP => {}
+            Instruction::Indexed(..) => unreachable!("indexed instructions run once resolved"),
+        }
+        Next::Continue
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    LDA(u64, Field),
+    LDX(u64, Field),
+    LDI(IndexReg, u64, Field),
+    LDAN(u64, Field),
+    LDXN(u64, Field),
+    LDIN(IndexReg, u64, Field),
+    STA(u64, Field),
+    STX(u64, Field),
+    STI(IndexReg, u64, Field),
+    STJ(u64, Field),
+    STZ(u64, Field),
+    ENTA(i64),
+    ENTX(i64),
+    ENTI(IndexReg, i64),
+    ENNA(i64),
+    ENNX(i64),
+    ENNI(IndexReg, i64),
+    INCA(i64),
+    INCX(i64),
+    INCI(IndexReg, i64),
+    DECA(i64),
+    DECX(i64),
+    DECI(IndexReg, i64),
+    ADD(u64, Field),
+    SUB(u64, Field),
+    CMPA(u64, Field),
+    CMPX(u64, Field),
+    CMPI(IndexReg, u64, Field),
+    IN(u64, u8),
+    OUT(u64, u8),
+    IOC(i64, u8),
+    SLA(u64),
+    SRA(u64),
+    SLAX(u64),
+    SRAX(u64),
+    SLC(u64),
+    SRC(u64),
+    /// Shift rA and rX together by binary places, an extension for binary
+    /// machines.
+    SLB(u64),
+    SRB(u64),
+    MOVE(u64, u8),
+    JMP(u64),
+    JSJ(u64),
+    JOV(u64),
+    JNOV(u64),
+    JL(u64),
+    JE(u64),
+    JG(u64),
+    JGE(u64),
+    JNE(u64),
+    JLE(u64),
+    JA(RegisterTest, u64),
+    JX(RegisterTest, u64),
+    /// Jump if rA is even, an extension for binary machines, like JAO,
+    /// JXE and JXO.
+    JAE(u64),
+    JAO(u64),
+    JXE(u64),
+    JXO(u64),
+    JI(IndexReg, RegisterTest, u64),
+    JBUS(u64, u8),
+    JRED(u64, u8),
+    HLT,
+    NOP,
+    /// `M,I`: the instruction with rIi added to its address when it runs.
+    Indexed(IndexReg, Box<Instruction>),
+}
+
+impl Instruction {
+    pub fn index_reg(&self) -> Option<IndexReg> {
+        match self {
+            Instruction::LDI(n, ..)
+            | Instruction::LDIN(n, ..)
+            | Instruction::STI(n, ..)
+            | Instruction::ENTI(n, _)
+            | Instruction::ENNI(n, _)
+            | Instruction::INCI(n, _)
+            | Instruction::DECI(n, _)
+            | Instruction::CMPI(n, ..)
+            | Instruction::JI(n, ..) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn is_extended(&self) -> bool {
+        match self {
+            Instruction::Indexed(n, instruction) => n.is_extended() || instruction.is_extended(),
+            _ => self.is_binary() || self.index_reg().is_some_and(|n| n.is_extended()),
+        }
+    }
+
+    /// Whether this is one of the binary extensions SLB, SRB, JAE, JAO,
+    /// JXE and JXO, which only run with a byte size that is a power of two.
+    pub fn is_binary(&self) -> bool {
+        match self {
+            Instruction::SLB(_)
+            | Instruction::SRB(_)
+            | Instruction::JAE(_)
+            | Instruction::JAO(_)
+            | Instruction::JXE(_)
+            | Instruction::JXO(_) => true,
+            Instruction::Indexed(_, instruction) => instruction.is_binary(),
+            _ => false,
+        }
+    }
+
+    /// The instruction with `offset` added to its address M, or None if M
+    /// would become negative where it must be an address or a count.
+    pub fn offset_address(&self, offset: i64) -> Option<Instruction> {
+        let m = |addr: &u64| u64::try_from(*addr as i64 + offset).ok();
+        Some(match self {
+            Instruction::LDA(addr, field) => Instruction::LDA(m(addr)?, *field),
+            Instruction::LDX(addr, field) => Instruction::LDX(m(addr)?, *field),
+            Instruction::LDI(n, addr, field) => Instruction::LDI(*n, m(addr)?, *field),
+            Instruction::LDAN(addr, field) => Instruction::LDAN(m(addr)?, *field),
+            Instruction::LDXN(addr, field) => Instruction::LDXN(m(addr)?, *field),
+            Instruction::LDIN(n, addr, field) => Instruction::LDIN(*n, m(addr)?, *field),
+            Instruction::STA(addr, field) => Instruction::STA(m(addr)?, *field),
+            Instruction::STX(addr, field) => Instruction::STX(m(addr)?, *field),
+            Instruction::STI(n, addr, field) => Instruction::STI(*n, m(addr)?, *field),
+            Instruction::STJ(addr, field) => Instruction::STJ(m(addr)?, *field),
+            Instruction::STZ(addr, field) => Instruction::STZ(m(addr)?, *field),
+            Instruction::ENTA(value) => Instruction::ENTA(value + offset),
+            Instruction::ENTX(value) => Instruction::ENTX(value + offset),
+            Instruction::ENTI(n, value) => Instruction::ENTI(*n, value + offset),
+            Instruction::ENNA(value) => Instruction::ENNA(value + offset),
+            Instruction::ENNX(value) => Instruction::ENNX(value + offset),
+            Instruction::ENNI(n, value) => Instruction::ENNI(*n, value + offset),
+            Instruction::INCA(value) => Instruction::INCA(value + offset),
+            Instruction::INCX(value) => Instruction::INCX(value + offset),
+            Instruction::INCI(n, value) => Instruction::INCI(*n, value + offset),
+            Instruction::DECA(value) => Instruction::DECA(value + offset),
+            Instruction::DECX(value) => Instruction::DECX(value + offset),
+            Instruction::DECI(n, value) => Instruction::DECI(*n, value + offset),
+            Instruction::ADD(addr, field) => Instruction::ADD(m(addr)?, *field),
+            Instruction::SUB(addr, field) => Instruction::SUB(m(addr)?, *field),
+            Instruction::CMPA(addr, field) => Instruction::CMPA(m(addr)?, *field),
+            Instruction::CMPX(addr, field) => Instruction::CMPX(m(addr)?, *field),
+            Instruction::CMPI(n, addr, field) => Instruction::CMPI(*n, m(addr)?, *field),
+            Instruction::IN(addr, unit) => Instruction::IN(m(addr)?, *unit),
+            Instruction::OUT(addr, unit) => Instruction::OUT(m(addr)?, *unit),
+            Instruction::IOC(value, unit) => Instruction::IOC(value + offset, *unit),
+            Instruction::SLA(count) => Instruction::SLA(m(count)?),
+            Instruction::SRA(count) => Instruction::SRA(m(count)?),
+            Instruction::SLAX(count) => Instruction::SLAX(m(count)?),
+            Instruction::SRAX(count) => Instruction::SRAX(m(count)?),
+            Instruction::SLC(count) => Instruction::SLC(m(count)?),
+            Instruction::SRC(count) => Instruction::SRC(m(count)?),
+            Instruction::SLB(count) => Instruction::SLB(m(count)?),
+            Instruction::SRB(count) => Instruction::SRB(m(count)?),
+            Instruction::MOVE(addr, count) => Instruction::MOVE(m(addr)?, *count),
+            Instruction::JMP(addr) => Instruction::JMP(m(addr)?),
+            Instruction::JSJ(addr) => Instruction::JSJ(m(addr)?),
+            Instruction::JOV(addr) => Instruction::JOV(m(addr)?),
+            Instruction::JNOV(addr) => Instruction::JNOV(m(addr)?),
+            Instruction::JL(addr) => Instruction::JL(m(addr)?),
+            Instruction::JE(addr) => Instruction::JE(m(addr)?),
+            Instruction::JG(addr) => Instruction::JG(m(addr)?),
+            Instruction::JGE(addr) => Instruction::JGE(m(addr)?),
+            Instruction::JNE(addr) => Instruction::JNE(m(addr)?),
+            Instruction::JLE(addr) => Instruction::JLE(m(addr)?),
+            Instruction::JA(test, addr) => Instruction::JA(*test, m(addr)?),
+            Instruction::JX(test, addr) => Instruction::JX(*test, m(addr)?),
+            Instruction::JAE(addr) => Instruction::JAE(m(addr)?),
+            Instruction::JAO(addr) => Instruction::JAO(m(addr)?),
+            Instruction::JXE(addr) => Instruction::JXE(m(addr)?),
+            Instruction::JXO(addr) => Instruction::JXO(m(addr)?),
+            Instruction::JI(n, test, addr) => Instruction::JI(*n, *test, m(addr)?),
+            Instruction::JBUS(addr, unit) => Instruction::JBUS(m(addr)?, *unit),
+            Instruction::JRED(addr, unit) => Instruction::JRED(m(addr)?, *unit),
+            Instruction::HLT | Instruction::NOP => self.clone(),
+            Instruction::Indexed(n, instruction) => {
+                Instruction::Indexed(*n, Box::new(instruction.offset_address(offset)?))
+            }
+        })
+    }
+
+    /// Execution time in MIX units (u), from TAOCP 1.3.1 table 1.
+    pub fn time(&self) -> u64 {
+        match self {
+            Instruction::HLT => 10,
+            Instruction::NOP
+            | Instruction::ENTA(_)
+            | Instruction::ENTX(_)
+            | Instruction::ENTI(..)
+            | Instruction::ENNA(_)
+            | Instruction::ENNX(_)
+            | Instruction::ENNI(..)
+            | Instruction::INCA(_)
+            | Instruction::INCX(_)
+            | Instruction::INCI(..)
+            | Instruction::DECA(_)
+            | Instruction::DECX(_)
+            | Instruction::DECI(..)
+            | Instruction::IN(..)
+            | Instruction::OUT(..)
+            | Instruction::IOC(..) => 1,
+            _ if self.jump_address().is_some() => 1,
+            Instruction::MOVE(_, count) => 1 + 2 * *count as u64,
+            Instruction::Indexed(_, instruction) => instruction.time(),
+            _ => 2,
+        }
+    }
+
+    /// Memory words this instruction reads or writes.
+    pub fn mems(&self) -> u64 {
+        match self {
+            Instruction::MOVE(_, count) => 2 * *count as u64,
+            Instruction::Indexed(_, instruction) => instruction.mems(),
+            _ => self.memory_operand().is_some() as u64,
+        }
+    }
+
+    /// The memory address this instruction reads or writes, if any. An
+    /// indexed instruction's address is only known when it runs.
+    pub fn memory_operand(&self) -> Option<u64> {
+        match self {
+            Instruction::LDA(addr, _)
+            | Instruction::LDX(addr, _)
+            | Instruction::LDI(_, addr, _)
+            | Instruction::LDAN(addr, _)
+            | Instruction::LDXN(addr, _)
+            | Instruction::LDIN(_, addr, _)
+            | Instruction::ADD(addr, _)
+            | Instruction::SUB(addr, _)
+            | Instruction::CMPA(addr, _)
+            | Instruction::CMPX(addr, _)
+            | Instruction::CMPI(_, addr, _) => Some(*addr),
+            _ => self.store_address(),
+        }
+    }
+
+    /// Whether this is a jump, indexed or not.
+    pub fn is_jump(&self) -> bool {
+        match self {
+            Instruction::Indexed(_, instruction) => instruction.is_jump(),
+            _ => self.jump_address().is_some(),
+        }
+    }
+
+    /// The location this instruction may jump to, if it is a jump. An
+    /// indexed jump's target is only known when it runs.
+    pub fn jump_address(&self) -> Option<u64> {
+        match self {
+            Instruction::JMP(addr)
+            | Instruction::JSJ(addr)
+            | Instruction::JOV(addr)
+            | Instruction::JNOV(addr)
+            | Instruction::JL(addr)
+            | Instruction::JE(addr)
+            | Instruction::JG(addr)
+            | Instruction::JGE(addr)
+            | Instruction::JNE(addr)
+            | Instruction::JLE(addr)
+            | Instruction::JA(_, addr)
+            | Instruction::JX(_, addr)
+            | Instruction::JAE(addr)
+            | Instruction::JAO(addr)
+            | Instruction::JXE(addr)
+            | Instruction::JXO(addr)
+            | Instruction::JI(_, _, addr)
+            | Instruction::JBUS(addr, _)
+            | Instruction::JRED(addr, _) => Some(*addr),
+            _ => None,
+        }
+    }
+
+    /// The memory address this instruction reads, writes or jumps to, if
+    /// it uses one: the first word of the block for IN, OUT and MOVE.
+    pub fn address(&self) -> Option<u64> {
+        match self {
+            Instruction::IN(addr, _) | Instruction::OUT(addr, _) | Instruction::MOVE(addr, _) => {
+                Some(*addr)
+            }
+            _ => self.memory_operand().or_else(|| self.jump_address()),
+        }
+    }
+
+    /// The memory address this instruction writes, if it is a store.
+    pub fn store_address(&self) -> Option<u64> {
+        match self {
+            Instruction::STA(addr, _)
+            | Instruction::STX(addr, _)
+            | Instruction::STI(_, addr, _)
+            | Instruction::STJ(addr, _)
+            | Instruction::STZ(addr, _) => Some(*addr),
+            _ => None,
+        }
+    }
+}
+
+// an address followed by its field specification unless it is the default
+struct Operand(u64, Field, Field);
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Operand(addr, field, default) = self;
+        if field == default {
+            write!(f, "{}", addr)
+        } else {
+            write!(f, "{}{}", addr, field)
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::LDA(addr, field) => {
+                write!(f, "LDA {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::LDX(addr, field) => {
+                write!(f, "LDX {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::LDI(n, addr, field) => {
+                write!(
+                    f,
+                    "LD{} {}",
+                    n.number(),
+                    Operand(*addr, *field, Field::WORD)
+                )
+            }
+            Instruction::LDAN(addr, field) => {
+                write!(f, "LDAN {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::LDXN(addr, field) => {
+                write!(f, "LDXN {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::LDIN(n, addr, field) => {
+                write!(
+                    f,
+                    "LD{}N {}",
+                    n.number(),
+                    Operand(*addr, *field, Field::WORD)
+                )
+            }
+            Instruction::STA(addr, field) => {
+                write!(f, "STA {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::STX(addr, field) => {
+                write!(f, "STX {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::STI(n, addr, field) => {
+                write!(
+                    f,
+                    "ST{} {}",
+                    n.number(),
+                    Operand(*addr, *field, Field::WORD)
+                )
+            }
+            Instruction::STJ(addr, field) => {
+                write!(f, "STJ {}", Operand(*addr, *field, Field::ADDRESS))
+            }
+            Instruction::STZ(addr, field) => {
+                write!(f, "STZ {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::ENTA(value) => write!(f, "ENTA {}", value),
+            Instruction::ENTX(value) => write!(f, "ENTX {}", value),
+            Instruction::ENTI(n, value) => write!(f, "ENT{} {}", n.number(), value),
+            Instruction::ENNA(value) => write!(f, "ENNA {}", value),
+            Instruction::ENNX(value) => write!(f, "ENNX {}", value),
+            Instruction::ENNI(n, value) => write!(f, "ENN{} {}", n.number(), value),
+            Instruction::INCA(value) => write!(f, "INCA {}", value),
+            Instruction::INCX(value) => write!(f, "INCX {}", value),
+            Instruction::INCI(n, value) => write!(f, "INC{} {}", n.number(), value),
+            Instruction::DECA(value) => write!(f, "DECA {}", value),
+            Instruction::DECX(value) => write!(f, "DECX {}", value),
+            Instruction::DECI(n, value) => write!(f, "DEC{} {}", n.number(), value),
+            Instruction::ADD(addr, field) => {
+                write!(f, "ADD {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::SUB(addr, field) => {
+                write!(f, "SUB {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::CMPA(addr, field) => {
+                write!(f, "CMPA {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::CMPX(addr, field) => {
+                write!(f, "CMPX {}", Operand(*addr, *field, Field::WORD))
+            }
+            Instruction::CMPI(n, addr, field) => {
+                write!(
+                    f,
+                    "CMP{} {}",
+                    n.number(),
+                    Operand(*addr, *field, Field::WORD)
+                )
+            }
+            Instruction::IN(addr, unit) => write!(f, "IN {}({})", addr, unit),
+            Instruction::OUT(addr, unit) => write!(f, "OUT {}({})", addr, unit),
+            Instruction::IOC(m, unit) => write!(f, "IOC {}({})", m, unit),
+            Instruction::SLA(count) => write!(f, "SLA {}", count),
+            Instruction::SRA(count) => write!(f, "SRA {}", count),
+            Instruction::SLAX(count) => write!(f, "SLAX {}", count),
+            Instruction::SRAX(count) => write!(f, "SRAX {}", count),
+            Instruction::SLC(count) => write!(f, "SLC {}", count),
+            Instruction::SRC(count) => write!(f, "SRC {}", count),
+            Instruction::SLB(count) => write!(f, "SLB {}", count),
+            Instruction::SRB(count) => write!(f, "SRB {}", count),
+            Instruction::MOVE(addr, count) => write!(f, "MOVE {}({})", addr, count),
+            Instruction::JMP(addr) => write!(f, "JMP {}", addr),
+            Instruction::JSJ(addr) => write!(f, "JSJ {}", addr),
+            Instruction::JOV(addr) => write!(f, "JOV {}", addr),
+            Instruction::JNOV(addr) => write!(f, "JNOV {}", addr),
+            Instruction::JL(addr) => write!(f, "JL {}", addr),
+            Instruction::JE(addr) => write!(f, "JE {}", addr),
+            Instruction::JG(addr) => write!(f, "JG {}", addr),
+            Instruction::JGE(addr) => write!(f, "JGE {}", addr),
+            Instruction::JNE(addr) => write!(f, "JNE {}", addr),
+            Instruction::JLE(addr) => write!(f, "JLE {}", addr),
+            Instruction::JA(test, addr) => write!(f, "JA{} {}", test, addr),
+            Instruction::JX(test, addr) => write!(f, "JX{} {}", test, addr),
+            Instruction::JAE(addr) => write!(f, "JAE {}", addr),
+            Instruction::JAO(addr) => write!(f, "JAO {}", addr),
+            Instruction::JXE(addr) => write!(f, "JXE {}", addr),
+            Instruction::JXO(addr) => write!(f, "JXO {}", addr),
+            Instruction::JI(n, test, addr) => write!(f, "J{}{} {}", n.number(), test, addr),
+            Instruction::JBUS(addr, unit) => write!(f, "JBUS {}({})", addr, unit),
+            Instruction::JRED(addr, unit) => write!(f, "JRED {}({})", addr, unit),
+            Instruction::Indexed(n, instruction) => {
+                // the index goes between the address and any field
+                let text = instruction.to_string();
+                let (address, field) = text.split_at(text.find('(').unwrap_or(text.len()));
+                write!(f, "{},{}{}", address, n.number(), field)
+            }
+            Instruction::HLT => write!(f, "HLT"),
+            Instruction::NOP => write!(f, "NOP"),
+        }
+    }
+}
+
+const MAX_INSTRUCTION_LENGTH: usize = 4;
+const MAX_LABEL_LENGTH: usize = 10;
+
+pub struct Program {
+    scanner: Scanner,
+    instructions: Vec<Instruction>,
+    line: usize,
+    // original line numbers when built from extracted lines
+    line_numbers: Vec<usize>,
+    instruction_set: InstructionSet,
+    // the highest index register the machine has
+    index_registers: u8,
+    byte_size: u8,
+    labels: BTreeMap<String, u64>,
+    // symbols given an absolute value by EQU
+    equates: BTreeMap<String, i64>,
+    // the label of the statement being parsed, for EQU
+    label: Option<String>,
+    // addresses that use labels defined further down
+    references: Vec<Reference>,
+    // instructions whose address is a label, which moves with the program
+    relocatable: BTreeSet<usize>,
+    entry_point: Option<u64>,
+    // a mnemonic read while looking past a label, for the next statement
+    next: Option<String>,
+    // the HLT of each ASSERT and what it reports
+    assertions: BTreeMap<u64, Assertion>,
+}
+
+impl Program {
+    pub fn new(input: &str) -> Self {
+        Self::with_instruction_set(input, InstructionSet::default())
+    }
+
+    pub fn with_instruction_set(input: &str, instruction_set: InstructionSet) -> Self {
+        Self {
+            scanner: Scanner::new(input),
+            instructions: Vec::new(),
+            line: 0,
+            line_numbers: Vec::new(),
+            instruction_set,
+            index_registers: match instruction_set {
+                InstructionSet::Strict => STRICT_INDEX_REGISTERS,
+                InstructionSet::Extended => MAX_INDEX_REGISTER,
+            },
+            byte_size: DEFAULT_BYTE_SIZE,
+            labels: BTreeMap::new(),
+            equates: BTreeMap::new(),
+            label: None,
+            references: Vec::new(),
+            relocatable: BTreeSet::new(),
+            entry_point: None,
+            next: None,
+            assertions: BTreeMap::new(),
+        }
+    }
+
+    /// A program for a machine with `config`, which also rejects index
+    /// registers the machine does not have and assembles for its byte
+    /// size.
+    pub fn with_config(input: &str, config: &MachineConfig) -> Self {
+        let mut program = Self::with_instruction_set(input, config.instruction_set);
+        program.index_registers = program.index_registers.min(config.index_registers as u8);
+        program.byte_size = config.byte_size;
+        program
+    }
+
+    /// Build a program from numbered lines, such as a snippet extracted
+    /// from a larger document. Diagnostics report the given numbers.
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = (usize, &'a str)>) -> Self {
+        let (line_numbers, text): (Vec<usize>, Vec<&str>) = lines.into_iter().unzip();
+        let mut program = Self::new(&text.join("\n"));
+        program.line_numbers = line_numbers;
+        program
+    }
+
+    fn source_line(&self) -> usize {
+        match self.line_numbers.get(self.line) {
+            Some(&line) => line,
+            None if self.line_numbers.is_empty() => self.line,
+            None => self.line_numbers[self.line_numbers.len() - 1],
+        }
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// The address named by `END`, counting instructions from 0 as labels
+    /// do. Programs without one start at their first instruction.
+    pub fn entry_point(&self) -> Option<u64> {
+        self.entry_point
+    }
+
+    /// The assertion reported by the HLT of each `ASSERT`, by the HLT's
+    /// instruction number.
+    pub fn assertions(&self) -> &BTreeMap<u64, Assertion> {
+        &self.assertions
+    }
+
+    /// The address of every label, counting instructions from 0 as jumps
+    /// do. Optimizing does not update them.
+    pub fn labels(&self) -> &BTreeMap<String, u64> {
+        &self.labels
+    }
+
+    /// The value of every symbol `EQU` defines as a number rather than
+    /// an address in the program.
+    pub fn equates(&self) -> &BTreeMap<String, i64> {
+        &self.equates
+    }
+
+    /// One instruction per line in the form `Display` prints, so programs
+    /// that differ only in spacing, separators or comments compare equal.
+    pub fn to_canonical_string(&self) -> String {
+        let mut text: String = self
+            .instructions
+            .iter()
+            .map(|instruction| format!("{}\n", instruction))
+            .collect();
+        if let Some(entry) = self.entry_point {
+            text.push_str(&format!("END {}\n", entry));
+        }
+        text
+    }
+
+    /// Encode every instruction as a MIX word.
+    pub fn assemble(&self) -> Vec<Word> {
+        self.assemble_at(0)
+    }
+
+    /// Encode every instruction as a MIX word for loading at `origin`.
+    /// Jump addresses and labels count instructions from 0, so `origin` is
+    /// added to them.
+    pub fn assemble_at(&self, origin: u64) -> Vec<Word> {
+        self.instructions
+            .iter()
+            .enumerate()
+            .map(|(pc, instruction)| {
+                let relocated = if instruction.is_jump() || self.relocatable.contains(&pc) {
+                    instruction.offset_address(origin as i64)
+                } else {
+                    Some(instruction.clone())
+                };
+                match relocated.and_then(|instruction| instruction.to_word(self.byte_size)) {
+                    Some(word) => word,
+                    None => panic!(
+                        "Instruction {:?} cannot be encoded as a MIX word",
+                        instruction
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    pub fn parse_with(&mut self, optimize: bool) -> Option<OptimizeReport> {
+        self.parse();
+        if !optimize {
+            return None;
+        }
+        let mut anchors: Vec<u64> = self
+            .entry_point
+            .iter()
+            .chain(self.assertions.keys())
+            .copied()
+            .collect();
+        let report =
+            optimize::optimize(&mut self.instructions, &mut self.relocatable, &mut anchors);
+        let mut anchors = anchors.into_iter();
+        if self.entry_point.is_some() {
+            self.entry_point = anchors.next();
+        }
+        self.assertions = std::mem::take(&mut self.assertions)
+            .into_values()
+            .zip(anchors)
+            .map(|(assertion, pc)| (pc, assertion))
+            .collect();
+        Some(report)
+    }
+
+    pub fn parse(&mut self) {
+        while let Some(instruction) = self.parse_instruction() {
+            // only labels and ASSERT are longer than a mnemonic
+            if instruction.len() > MAX_INSTRUCTION_LENGTH
+                && instruction != "ASSERT"
+                && !self.label_follows()
+            {
+                panic!("Invalid instruction at line {}", self.source_line())
+            }
+            match instruction.as_str() {
+                "ADD" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::ADD(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "SUB" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::SUB(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "STA" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::STA(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "STX" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::STX(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ST1" | "ST2" | "ST3" | "ST4" | "ST5" | "ST6" | "ST7" | "ST8" | "ST9" | "ST10" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[2..]);
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::STI(n, value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "STJ" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::ADDRESS) {
+                        self.push(Instruction::STJ(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "STZ" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::STZ(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ENTA" => {
+                    if let Some((value, index)) = self.parse_value() {
+                        self.push(Instruction::ENTA(value), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ENTX" => {
+                    if let Some((value, index)) = self.parse_value() {
+                        self.push(Instruction::ENTX(value), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ENT1" | "ENT2" | "ENT3" | "ENT4" | "ENT5" | "ENT6" | "ENT7" | "ENT8" | "ENT9" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[3..]);
+                    if let Some((value, index)) = self.parse_value() {
+                        self.push(Instruction::ENTI(n, value), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ENNA" => {
+                    if let Some((value, index)) = self.parse_value() {
+                        self.push(Instruction::ENNA(value), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ENNX" => {
+                    if let Some((value, index)) = self.parse_value() {
+                        self.push(Instruction::ENNX(value), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "ENN1" | "ENN2" | "ENN3" | "ENN4" | "ENN5" | "ENN6" | "ENN7" | "ENN8" | "ENN9" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[3..]);
+                    if let Some((value, index)) = self.parse_value() {
+                        self.push(Instruction::ENNI(n, value), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "INCA" | "INCX" | "DECA" | "DECX" => {
+                    let (value, index) = self.parse_value().unwrap_or_else(|| {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    });
+                    let instruction = match instruction.as_str() {
+                        "INCA" => Instruction::INCA(value),
+                        "INCX" => Instruction::INCX(value),
+                        "DECA" => Instruction::DECA(value),
+                        _ => Instruction::DECX(value),
+                    };
+                    self.push(instruction, index);
+                }
+                "INC1" | "INC2" | "INC3" | "INC4" | "INC5" | "INC6" | "INC7" | "INC8" | "INC9"
+                | "DEC1" | "DEC2" | "DEC3" | "DEC4" | "DEC5" | "DEC6" | "DEC7" | "DEC8"
+                | "DEC9" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[3..]);
+                    let (value, index) = self.parse_value().unwrap_or_else(|| {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    });
+                    let instruction = if instruction.starts_with("INC") {
+                        Instruction::INCI(n, value)
+                    } else {
+                        Instruction::DECI(n, value)
+                    };
+                    self.push(instruction, index);
+                }
+                "LDA" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::LDA(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "LDX" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::LDX(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "LD1" | "LD2" | "LD3" | "LD4" | "LD5" | "LD6" | "LD7" | "LD8" | "LD9" | "LD10" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[2..]);
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::LDI(n, value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "LDAN" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::LDAN(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "LDXN" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::LDXN(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "LD1N" | "LD2N" | "LD3N" | "LD4N" | "LD5N" | "LD6N" | "LD7N" | "LD8N" | "LD9N" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[2..3]);
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::LDIN(n, value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "CMPA" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::CMPA(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "CMPX" => {
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::CMPX(value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "CMP1" | "CMP2" | "CMP3" | "CMP4" | "CMP5" | "CMP6" | "CMP7" | "CMP8" | "CMP9" => {
+                    let n = self.parse_index_reg(&instruction, &instruction[3..]);
+                    if let Some((value, index, field)) = self.parse_address(Field::WORD) {
+                        self.push(Instruction::CMPI(n, value, field), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "IN" | "OUT" => {
+                    let (addr, index, unit) = self
+                        .parse_byte_operand("unit", UNITS)
+                        .and_then(|(m, index, unit)| Some((u64::try_from(m).ok()?, index, unit)))
+                        .unwrap_or_else(|| {
+                            panic!("Invalid instruction at line {}", self.source_line())
+                        });
+                    let io = if instruction == "IN" {
+                        Instruction::IN(addr, unit)
+                    } else {
+                        Instruction::OUT(addr, unit)
+                    };
+                    self.push(io, index);
+                }
+                "IOC" => {
+                    if let Some((m, index, unit)) = self.parse_byte_operand("unit", UNITS) {
+                        self.push(Instruction::IOC(m, unit), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "SLA" | "SRA" | "SLAX" | "SRAX" | "SLC" | "SRC" | "SLB" | "SRB" => {
+                    let (count, index) = self
+                        .parse_value()
+                        .and_then(|(count, index)| Some((u64::try_from(count).ok()?, index)))
+                        .unwrap_or_else(|| {
+                            panic!("Invalid instruction at line {}", self.source_line())
+                        });
+                    let shift = match instruction.as_str() {
+                        "SLA" => Instruction::SLA(count),
+                        "SRA" => Instruction::SRA(count),
+                        "SLAX" => Instruction::SLAX(count),
+                        "SRAX" => Instruction::SRAX(count),
+                        "SLC" => Instruction::SLC(count),
+                        "SRC" => Instruction::SRC(count),
+                        "SLB" => Instruction::SLB(count),
+                        _ => Instruction::SRB(count),
+                    };
+                    if shift.is_binary() {
+                        self.check_binary(&instruction);
+                    }
+                    self.push(shift, index);
+                }
+                "MOVE" => {
+                    if let Some((addr, index, count)) =
+                        self.parse_byte_operand("count", self.byte_size)
+                    {
+                        let addr = u64::try_from(addr).unwrap_or_else(|_| {
+                            panic!("Invalid instruction at line {}", self.source_line())
+                        });
+                        self.push(Instruction::MOVE(addr, count), index);
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+                "JMP" | "JSJ" | "JOV" | "JNOV" | "JL" | "JE" | "JG" | "JGE" | "JNE" | "JLE" => {
+                    let (addr, index) = self.parse_jump_address();
+                    let jump = match instruction.as_str() {
+                        "JMP" => Instruction::JMP(addr),
+                        "JSJ" => Instruction::JSJ(addr),
+                        "JOV" => Instruction::JOV(addr),
+                        "JNOV" => Instruction::JNOV(addr),
+                        "JL" => Instruction::JL(addr),
+                        "JE" => Instruction::JE(addr),
+                        "JG" => Instruction::JG(addr),
+                        "JGE" => Instruction::JGE(addr),
+                        "JNE" => Instruction::JNE(addr),
+                        _ => Instruction::JLE(addr),
+                    };
+                    self.push(jump, index);
+                }
+                "JBUS" | "JRED" => {
+                    let (addr, index, unit) = self
+                        .parse_byte_operand("unit", UNITS)
+                        .and_then(|(m, index, unit)| Some((u64::try_from(m).ok()?, index, unit)))
+                        .unwrap_or_else(|| {
+                            panic!("Invalid instruction at line {}", self.source_line())
+                        });
+                    let jump = if instruction == "JBUS" {
+                        Instruction::JBUS(addr, unit)
+                    } else {
+                        Instruction::JRED(addr, unit)
+                    };
+                    self.push(jump, index);
+                }
+                "JAE" | "JAO" | "JXE" | "JXO" => {
+                    self.check_binary(&instruction);
+                    let (addr, index) = self.parse_jump_address();
+                    let jump = match instruction.as_str() {
+                        "JAE" => Instruction::JAE(addr),
+                        "JAO" => Instruction::JAO(addr),
+                        "JXE" => Instruction::JXE(addr),
+                        _ => Instruction::JXO(addr),
+                    };
+                    self.push(jump, index);
+                }
+                "ASSERT" => self.parse_assertion(),
+                // END before a mnemonic is a label, as in `END HLT`
+                "END" => match self.parse_operand() {
+                    Some(name) if is_mnemonic(&name) => {
+                        self.define_label(instruction);
+                        self.next = Some(name);
+                        continue;
+                    }
+                    Some(operand) => {
+                        let entry = self.defined_value(&operand).value;
+                        self.entry_point = Some(u64::try_from(entry).unwrap_or_else(|_| {
+                            panic!("Invalid value at line {}", self.source_line())
+                        }));
+                        break;
+                    }
+                    None => panic!("Invalid instruction at line {}", self.source_line()),
+                },
+                "EQU" => self.parse_equ(),
+                "HLT" => self.instructions.push(Instruction::HLT),
+                "NOP" => self.instructions.push(Instruction::NOP),
+                // JAN, JXNZ, J1P and the rest of the register jumps
+                _ if instruction.starts_with('J')
+                    && (register_test(&instruction).is_some() || !self.label_follows()) =>
+                {
+                    let test = register_test(&instruction).unwrap_or_else(|| {
+                        panic!("Unknown instruction at line {}", self.source_line())
+                    });
+                    let register = instruction.as_bytes()[1];
+                    let n = register
+                        .is_ascii_digit()
+                        .then(|| self.parse_index_reg(&instruction, &instruction[1..2]));
+                    let (addr, index) = self.parse_jump_address();
+                    let jump = match (register, n) {
+                        (_, Some(n)) => Instruction::JI(n, test, addr),
+                        (b'A', _) => Instruction::JA(test, addr),
+                        (b'X', _) => Instruction::JX(test, addr),
+                        _ => panic!("Unknown instruction at line {}", self.source_line()),
+                    };
+                    self.push(jump, index);
+                }
+                _ if self.label_follows() => {
+                    self.define_label(instruction);
+                    continue;
+                }
+                _ => panic!("Unknown instruction at line {}", self.source_line()),
+            }
+            self.label = None;
+            self.skip_remarks();
+        }
+        self.resolve_labels();
+    }
+
+    pub fn parse_instruction(&mut self) -> Option<String> {
+        if let Some(instruction) = self.next.take() {
+            return Some(instruction);
+        }
+        let mut instruction = String::new();
+        // the delimiter after a name is left for the operand or the next call
+        while let Some(&c) = self.scanner.peek() {
+            match c {
+                ' ' | '\t' | '\r' | ';' | '\n' | '#' if !instruction.is_empty() => break,
+                ' ' | '\t' | '\r' | ';' => {}
+                '\n' => self.line += 1,
+                // `*` starts a comment line in TAOCP listings
+                '#' | '*' => {
+                    self.skip_comment();
+                    continue;
+                }
+                _ => {
+                    if c.is_ascii_uppercase() || (!instruction.is_empty() && c.is_ascii_digit()) {
+                        instruction.push(c)
+                    } else {
+                        panic!("Invalid instruction at line {}", self.source_line())
+                    }
+                }
+            }
+            self.scanner.pop();
+        }
+        if instruction.is_empty() {
+            return None;
+        }
+        if instruction.len() > MAX_LABEL_LENGTH {
+            panic!("Invalid instruction at line {}", self.source_line())
+        }
+        Some(instruction)
+    }
+
+    // the register numbered by all of `digits`, the part of `instruction`
+    // that names it
+    fn parse_index_reg(&self, instruction: &str, digits: &str) -> IndexReg {
+        let n = Some(digits)
+            .filter(|digits| !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<u8>().ok())
+            .and_then(|n| IndexReg::try_from(n).ok())
+            .unwrap_or_else(|| panic!("Invalid index register at line {}", self.source_line()));
+        if n.is_extended() && self.instruction_set == InstructionSet::Strict {
+            panic!(
+                "Extended instruction {} in strict mode at line {}",
+                instruction,
+                self.source_line()
+            )
+        }
+        self.check_configured(n);
+        n
+    }
+
+    // the binary instructions are extensions for byte sizes that are
+    // powers of two
+    fn check_binary(&self, instruction: &str) {
+        if self.instruction_set == InstructionSet::Strict {
+            panic!(
+                "Extended instruction {} in strict mode at line {}",
+                instruction,
+                self.source_line()
+            )
+        }
+        if !self.byte_size.is_power_of_two() {
+            panic!(
+                "Binary instruction {} with byte size {} at line {}",
+                instruction,
+                self.byte_size,
+                self.source_line()
+            )
+        }
+    }
+
+    fn check_configured(&self, n: IndexReg) {
+        if n.number() > self.index_registers {
+            panic!(
+                "Index register {} is not configured at line {}",
+                n.number(),
+                self.source_line()
+            )
+        }
+    }
+
+    fn parse_address(&mut self, default: Field) -> Option<(u64, Option<IndexReg>, Field)> {
+        let operand = self.parse_operand()?;
+        let (address, field) = match operand.find('(') {
+            Some(pos) => operand.split_at(pos),
+            None => (operand.as_str(), ""),
+        };
+        let (address, index) = self.signed_value(address)?;
+        let address = u64::try_from(address)
+            .unwrap_or_else(|_| panic!("Invalid value at line {}", self.source_line()));
+        if field.is_empty() {
+            return Some((address, index, default));
+        }
+        let field = field
+            .strip_prefix('(')
+            .and_then(|field| field.strip_suffix(')'))
+            .and_then(|field| field.split_once(':'))
+            .and_then(|(left, right)| Field::new(left.parse().ok()?, right.parse().ok()?))
+            .unwrap_or_else(|| {
+                panic!("Invalid field specification at line {}", self.source_line())
+            });
+        Some((address, index, field))
+    }
+
+    fn parse_jump_address(&mut self) -> (u64, Option<IndexReg>) {
+        self.parse_value()
+            .and_then(|(addr, index)| Some((u64::try_from(addr).ok()?, index)))
+            .unwrap_or_else(|| panic!("Invalid instruction at line {}", self.source_line()))
+    }
+
+    // `M(F)` where F is a plain byte below `limit` rather than a field,
+    // such as the unit of IN, OUT and IOC or the count of MOVE. F
+    // defaults to 0.
+    fn parse_byte_operand(&mut self, what: &str, limit: u8) -> Option<(i64, Option<IndexReg>, u8)> {
+        let operand = self.parse_operand()?;
+        let (m, byte) = match operand.find('(') {
+            Some(pos) => operand.split_at(pos),
+            None => (operand.as_str(), ""),
+        };
+        let (m, index) = self.signed_value(m)?;
+        if byte.is_empty() {
+            return Some((m, index, 0));
+        }
+        let byte = byte
+            .strip_prefix('(')
+            .and_then(|byte| byte.strip_suffix(')'))
+            .and_then(|byte| byte.parse().ok())
+            .filter(|&byte| byte < limit)
+            .unwrap_or_else(|| panic!("Invalid {} at line {}", what, self.source_line()));
+        Some((m, index, byte))
+    }
+
+    // the next operand, up to whitespace, a separator or a comment
+    fn parse_operand(&mut self) -> Option<String> {
+        self.skip_blanks();
+        let mut operand = String::new();
+        while let Some(&c) = self.scanner.peek() {
+            if matches!(c, ' ' | '\t' | '\r' | ';' | '\n' | '#') {
+                break;
+            }
+            operand.push(c);
+            self.scanner.pop();
+        }
+        if operand.is_empty() {
+            return None;
+        }
+        Some(operand)
+    }
+
+    fn parse_value(&mut self) -> Option<(i64, Option<IndexReg>)> {
+        let operand = self.parse_operand()?;
+        self.signed_value(&operand)
+    }
+
+    // the value of an expression and its index register, or None if the
+    // operand does not start with one. Labels defined further down count
+    // as 0 until `resolve_labels` adds them.
+    fn signed_value(&mut self, operand: &str) -> Option<(i64, Option<IndexReg>)> {
+        let (value, index) = self.split_index(operand);
+        let expression = self.expression(value)?;
+        let pc = self.instructions.len();
+        let line = self.source_line();
+        if expression.pending.is_empty() {
+            self.relocate(pc, expression.coefficient, line);
+            return Some((expression.value, index));
+        }
+        self.references.push(Reference {
+            pc,
+            expression,
+            line,
+        });
+        Some((0, index))
+    }
+
+    // `*`, numbers and symbols joined by `+ - * / // :`, which apply from
+    // left to right as in MIXAL, or None if `text` does not start with
+    // one of them
+    fn expression(&self, text: &str) -> Option<Expression> {
+        let (mut op, mut rest) = match text.strip_prefix(['+', '-']) {
+            Some(rest) => (&text[..1], rest),
+            None => ("+", text),
+        };
+        let mut value = Expression::default();
+        let mut first = true;
+        loop {
+            let atom = match self.atom(rest) {
+                Some(atom) => atom,
+                None if first => return None,
+                None => panic!("Invalid expression at line {}", self.source_line()),
+            };
+            first = false;
+            value = self.apply(value, op, atom.0);
+            rest = atom.1;
+            if rest.is_empty() {
+                return Some(value);
+            }
+            (op, rest) = ["//", "+", "-", "*", "/", ":"]
+                .into_iter()
+                .find_map(|op| Some((op, rest.strip_prefix(op)?)))
+                .unwrap_or_else(|| panic!("Invalid expression at line {}", self.source_line()));
+        }
+    }
+
+    // the `*`, number or symbol at the start of `text` and what follows it
+    fn atom<'a>(&self, text: &'a str) -> Option<(Expression, &'a str)> {
+        if let Some(rest) = text.strip_prefix('*') {
+            let here = Expression {
+                value: self.instructions.len() as i64,
+                coefficient: 1,
+                ..Expression::default()
+            };
+            return Some((here, rest));
+        }
+        let end = text
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(text.len());
+        let (name, rest) = text.split_at(end);
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            let value = name
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid value at line {}", self.source_line()));
+            return Some((Expression::number(value), rest));
+        }
+        if !is_label(name) {
+            return None;
+        }
+        let symbol = match (self.equates.get(name), self.labels.get(name)) {
+            (Some(&value), _) => Expression::number(value),
+            (_, Some(&addr)) => Expression {
+                value: addr as i64,
+                coefficient: 1,
+                ..Expression::default()
+            },
+            _ => Expression {
+                pending: vec![(name.to_string(), 1)],
+                ..Expression::default()
+            },
+        };
+        Some((symbol, rest))
+    }
+
+    // `left op right`, keeping count of how many times the program's
+    // origin the result includes
+    fn apply(&self, left: Expression, op: &str, right: Expression) -> Expression {
+        let line = self.source_line();
+        let invalid = || -> ! { panic!("Invalid value at line {}", line) };
+        if let "+" | "-" = op {
+            let sign = if op == "+" { 1 } else { -1 };
+            let mut pending = left.pending;
+            pending.extend(right.pending.into_iter().map(|(name, n)| (name, sign * n)));
+            return Expression {
+                value: left
+                    .value
+                    .checked_add(sign * right.value)
+                    .unwrap_or_else(|| invalid()),
+                coefficient: left.coefficient + sign * right.coefficient,
+                pending,
+            };
+        }
+        // labels defined further down are only added or subtracted
+        if let Some((name, _)) = left.pending.first().or(right.pending.first()) {
+            panic!("Undefined label {} at line {}", name, line)
+        }
+        let absolute = left.coefficient == 0 && right.coefficient == 0;
+        let (a, b) = (left.value, right.value);
+        let (value, coefficient) = match op {
+            "*" if left.coefficient == 0 || right.coefficient == 0 => (
+                a.checked_mul(b),
+                a * right.coefficient + left.coefficient * b,
+            ),
+            "/" if absolute && b != 0 => (Some(a / b), 0),
+            // the fraction a/b in bytes, as in MIXAL
+            "//" if absolute && b != 0 => {
+                let word = (self.byte_size as i128).pow(5);
+                (i64::try_from(a as i128 * word / b as i128).ok(), 0)
+            }
+            ":" if absolute => (a.checked_mul(8).and_then(|a| a.checked_add(b)), 0),
+            "/" | "//" if b == 0 => panic!("Division by zero at line {}", line),
+            _ => panic!("Invalid relocation at line {}", line),
+        };
+        Expression {
+            value: value.unwrap_or_else(|| invalid()),
+            coefficient,
+            pending: Vec::new(),
+        }
+    }
+
+    // an expression all of whose symbols are already defined
+    fn defined_value(&self, operand: &str) -> Expression {
+        let line = self.source_line();
+        let expression = self
+            .expression(operand)
+            .unwrap_or_else(|| panic!("Invalid value at line {}", line));
+        if let Some((name, _)) = expression.pending.first() {
+            panic!("Undefined label {} at line {}", name, line)
+        }
+        if !matches!(expression.coefficient, 0 | 1) {
+            panic!("Invalid relocation at line {}", line)
+        }
+        expression
+    }
+
+    // `NAME EQU expression` gives the statement's label the value of the
+    // expression rather than the number of the next instruction. An
+    // expression that includes the origin once is another label;
+    // otherwise it is a number.
+    fn parse_equ(&mut self) {
+        let line = self.source_line();
+        let Some(name) = self.label.take() else {
+            panic!("Invalid instruction at line {}", line)
+        };
+        self.labels.remove(&name);
+        let operand = self
+            .parse_operand()
+            .unwrap_or_else(|| panic!("Invalid instruction at line {}", line));
+        let expression = self.defined_value(&operand);
+        if expression.coefficient == 1 {
+            let addr = u64::try_from(expression.value)
+                .unwrap_or_else(|_| panic!("Invalid value at line {}", line));
+            self.labels.insert(name, addr);
+        } else {
+            self.equates.insert(name, expression.value);
+        }
+    }
+
+    // the instruction at `pc` moves with the program if its address
+    // includes the origin once
+    fn relocate(&mut self, pc: usize, coefficient: i64, line: usize) {
+        match coefficient {
+            0 => {}
+            1 => {
+                self.relocatable.insert(pc);
+            }
+            _ => panic!("Invalid relocation at line {}", line),
+        }
+    }
+
+    // split `M,I` into M and index register I
+    fn split_index<'a>(&self, operand: &'a str) -> (&'a str, Option<IndexReg>) {
+        let Some((m, index)) = operand.split_once(',') else {
+            return (operand, None);
+        };
+        let n = index
+            .parse::<u8>()
+            .ok()
+            .and_then(|n| IndexReg::try_from(n).ok())
+            .unwrap_or_else(|| panic!("Invalid index register at line {}", self.source_line()));
+        if n.is_extended() && self.instruction_set == InstructionSet::Strict {
+            panic!(
+                "Extended index register {} in strict mode at line {}",
+                n.number(),
+                self.source_line()
+            )
+        }
+        self.check_configured(n);
+        (m, Some(n))
+    }
+
+    // `ASSERT r,relation,0,"message"` becomes a jump to a HLT when the
+    // relation does not hold, and a JSJ over it, which leaves rJ alone
+    fn parse_assertion(&mut self) {
+        let line = self.source_line();
+        let invalid = || -> ! { panic!("Invalid assertion at line {}", line) };
+        let operand = self.parse_quoted_operand();
+        let (condition, message) = match operand.split_once(",\"") {
+            Some((condition, message)) => {
+                let message = message.strip_suffix('"').unwrap_or_else(|| invalid());
+                (condition, message.to_string())
+            }
+            None => (operand.as_str(), operand.clone()),
+        };
+        let [register, relation, value] = condition
+            .split(',')
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap_or_else(|_| invalid());
+        let test = match relation {
+            "<" => RegisterTest::Negative,
+            "=" => RegisterTest::Zero,
+            ">" => RegisterTest::Positive,
+            ">=" => RegisterTest::NonNegative,
+            "!=" => RegisterTest::NonZero,
+            "<=" => RegisterTest::NonPositive,
+            _ => invalid(),
+        }
+        .negated();
+        // MIX can only test the sign of a register
+        if value != "0" {
+            invalid()
+        }
+        let hlt = self.instructions.len() as u64 + 2;
+        let jump = match register {
+            "A" => Instruction::JA(test, hlt),
+            "X" => Instruction::JX(test, hlt),
+            digits if digits.starts_with(|c: char| c.is_ascii_digit()) => {
+                Instruction::JI(self.parse_index_reg("ASSERT", digits), test, hlt)
+            }
+            _ => invalid(),
+        };
+        self.instructions.push(jump);
+        self.instructions.push(Instruction::JSJ(hlt + 1));
+        self.instructions.push(Instruction::HLT);
+        self.assertions.insert(hlt, Assertion { message, line });
+    }
+
+    // the rest of the statement, which a `;` or `#` inside quotes does not
+    // end
+    fn parse_quoted_operand(&mut self) -> String {
+        self.skip_blanks();
+        let mut operand = String::new();
+        let mut quoted = false;
+        while let Some(&c) = self.scanner.peek() {
+            if c == '\n' || (!quoted && matches!(c, ';' | '#')) {
+                break;
+            }
+            quoted ^= c == '"';
+            operand.push(c);
+            self.scanner.pop();
+        }
+        operand.trim_end().to_string()
+    }
+
+    // add `instruction`, indexed by `index` if there is one
+    fn push(&mut self, instruction: Instruction, index: Option<IndexReg>) {
+        self.instructions.push(match index {
+            Some(n) => Instruction::Indexed(n, Box::new(instruction)),
+            None => instruction,
+        });
+    }
+
+    // up to the end of the line, which is left for `parse_instruction`
+    fn skip_comment(&mut self) {
+        while self.scanner.peek().is_some_and(|&c| c != '\n') {
+            self.scanner.pop();
+        }
+    }
+
+    // anything after the operand up to the end of the statement, such as
+    // the remarks column of a TAOCP listing
+    fn skip_remarks(&mut self) {
+        while self
+            .scanner
+            .peek()
+            .is_some_and(|&c| !matches!(c, ';' | '\n' | '#'))
+        {
+            self.scanner.pop();
+        }
+    }
+
+    fn skip_blanks(&mut self) {
+        while self
+            .scanner
+            .peek()
+            .is_some_and(|&c| matches!(c, ' ' | '\t' | '\r'))
+        {
+            self.scanner.pop();
+        }
+    }
+
+    // a name at the start of a statement that is not an instruction is a
+    // label if another name follows it
+    fn label_follows(&mut self) -> bool {
+        self.skip_blanks();
+        self.scanner.peek().is_some_and(char::is_ascii_uppercase)
+    }
+
+    fn define_label(&mut self, label: String) {
+        if self.labels.contains_key(&label) || self.equates.contains_key(&label) {
+            panic!("Duplicate label {} at line {}", label, self.source_line())
+        }
+        self.labels
+            .insert(label.clone(), self.instructions.len() as u64);
+        self.label = Some(label);
+    }
+
+    fn resolve_labels(&mut self) {
+        for Reference {
+            pc,
+            expression,
+            line,
+        } in std::mem::take(&mut self.references)
+        {
+            let mut value = expression.value;
+            let mut coefficient = expression.coefficient;
+            for (name, sign) in expression.pending {
+                match (self.equates.get(&name), self.labels.get(&name)) {
+                    (Some(&n), _) => value += sign * n,
+                    (_, Some(&addr)) => {
+                        value += sign * addr as i64;
+                        coefficient += sign;
+                    }
+                    _ => panic!("Undefined label {} at line {}", name, line),
+                }
+            }
+            self.instructions[pc] = self.instructions[pc]
+                .offset_address(value)
+                .unwrap_or_else(|| panic!("Invalid value at line {}", line));
+            self.relocate(pc, coefficient, line);
+        }
+    }
+}
+
+// a value in an operand: a number plus the origin of the program
+// `coefficient` times, plus or minus labels that are not defined yet
+#[derive(Debug, Default)]
+struct Expression {
+    value: i64,
+    coefficient: i64,
+    pending: Vec<(String, i64)>,
+}
+
+impl Expression {
+    fn number(value: i64) -> Self {
+        Self {
+            value,
+            ..Self::default()
+        }
+    }
+}
+
+// an operand that uses labels defined further down, for `resolve_labels`
+struct Reference {
+    pc: usize,
+    expression: Expression,
+    line: usize,
+}
+
+fn is_label(name: &str) -> bool {
+    name.len() <= MAX_LABEL_LENGTH
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+// whether `name` is an instruction rather than a label
+fn is_mnemonic(name: &str) -> bool {
+    const MNEMONICS: [&str; 52] = [
+        "ADD", "SUB", "STA", "STX", "STJ", "STZ", "ENTA", "ENTX", "ENNA", "ENNX", "INCA", "INCX",
+        "DECA", "DECX", "LDA", "LDX", "LDAN", "LDXN", "CMPA", "CMPX", "IN", "OUT", "IOC", "SLA",
+        "SRA", "SLAX", "SRAX", "SLC", "SRC", "SLB", "SRB", "MOVE", "JMP", "JSJ", "JOV", "JNOV",
+        "JL", "JE", "JG", "JGE", "JNE", "JLE", "JBUS", "JRED", "JAE", "JAO", "JXE", "JXO", "HLT",
+        "NOP", "ASSERT", "EQU",
+    ];
+    // ST1, ENN2, LD3N and the rest of the index register instructions
+    let indexed = |prefix: &str, suffix: &str| {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+            .is_some_and(|n| matches!(n.as_bytes(), [b'1'..=b'9']))
+    };
+    MNEMONICS.contains(&name)
+        || ["ST", "ENT", "ENN", "INC", "DEC", "LD", "CMP"]
+            .into_iter()
+            .any(|prefix| indexed(prefix, ""))
+        || indexed("LD", "N")
+        || (name.starts_with('J') && register_test(name).is_some())
+}
+
+fn register_test(instruction: &str) -> Option<RegisterTest> {
+    instruction.get(2..).and_then(RegisterTest::from_suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reg(n: u8) -> IndexReg {
+        IndexReg::try_from(n).unwrap()
+    }
+
+    const BYTE_SIZE: i64 = DEFAULT_BYTE_SIZE as i64;
+
+    fn words(values: &[i64]) -> Vec<Word> {
+        values.iter().map(|&value| Word::from(value)).collect()
+    }
+
+    fn extended_program(input: &str) -> Program {
+        Program::with_instruction_set(input, InstructionSet::Extended)
+    }
+
+    fn extended_mmix() -> MMix {
+        MMix::with_config(MachineConfig::new().with_instruction_set(InstructionSet::Extended))
+    }
+
+    #[test]
+    fn test_parse_instruction_add() {
+        let mut program = Program::new("ADD 100\n");
+        assert_eq!(program.parse_instruction(), Some("ADD".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_sub() {
+        let mut program = Program::new("SUB 100\n");
+        assert_eq!(program.parse_instruction(), Some("SUB".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_lda() {
+        let mut program = Program::new("LDA 100\n");
+        assert_eq!(program.parse_instruction(), Some("LDA".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_ldx() {
+        let mut program = Program::new("LDX 100\n");
+        assert_eq!(program.parse_instruction(), Some("LDX".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_ldi() {
+        for i in 1..10 {
+            let mut program = Program::new(format!("LD{} 100\n", i).as_str());
+            assert_eq!(program.parse_instruction(), Some(format!("LD{}", i)));
+        }
+    }
+
+    #[test]
+    fn test_parse_instruction_ldan() {
+        let mut program = Program::new("LDAN 100\n");
+        assert_eq!(program.parse_instruction(), Some("LDAN".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_ldxn() {
+        let mut program = Program::new("LDXN 100\n");
+        assert_eq!(program.parse_instruction(), Some("LDXN".to_string()));
+    }
+
+    #[test]
     fn test_parse_instruction_ldin() {
         for i in 1..10 {
-            let mut program = Program::new(format!("LD{}N 100\n", i).as_str());
-            assert_eq!(program.parse_instruction(), Some(format!("LD{}N", i)));
+            let mut program = Program::new(format!("LD{}N 100\n", i).as_str());
+            assert_eq!(program.parse_instruction(), Some(format!("LD{}N", i)));
+        }
+    }
+
+    #[test]
+    fn test_parse_instruction_sta() {
+        let mut program = Program::new("STA 100\n");
+        assert_eq!(program.parse_instruction(), Some("STA".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_stax() {
+        let mut program = Program::new("STX 100\n");
+        assert_eq!(program.parse_instruction(), Some("STX".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_sti() {
+        for i in 1..10 {
+            let mut program = Program::new(format!("ST{} 100\n", i).as_str());
+            assert_eq!(program.parse_instruction(), Some(format!("ST{}", i)));
+        }
+    }
+
+    #[test]
+    fn test_parse_instruction_stj() {
+        let mut program = Program::new("STJ 100\n");
+        assert_eq!(program.parse_instruction(), Some("STJ".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_stz() {
+        let mut program = Program::new("STZ 100\n");
+        assert_eq!(program.parse_instruction(), Some("STZ".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_enta() {
+        let mut program = Program::new("ENTA 100\n");
+        assert_eq!(program.parse_instruction(), Some("ENTA".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_entx() {
+        let mut program = Program::new("ENTX 100\n");
+        assert_eq!(program.parse_instruction(), Some("ENTX".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_enti() {
+        for i in 1..10 {
+            let mut program = Program::new(format!("ENT{} 100\n", i).as_str());
+            assert_eq!(program.parse_instruction(), Some(format!("ENT{}", i)));
+        }
+    }
+
+    #[test]
+    fn test_parse_instruction_enna() {
+        let mut program = Program::new("ENNA 100\n");
+        assert_eq!(program.parse_instruction(), Some("ENNA".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_ennx() {
+        let mut program = Program::new("ENNX 100\n");
+        assert_eq!(program.parse_instruction(), Some("ENNX".to_string()));
+    }
+
+    #[test]
+    fn test_parse_instruction_enni() {
+        for i in 1..10 {
+            let mut program = Program::new(format!("ENN{} 100\n", i).as_str());
+            assert_eq!(program.parse_instruction(), Some(format!("ENN{}", i)));
+        }
+    }
+    #[test]
+    fn test_parse_value() {
+        let mut program = Program::new("100\n");
+        assert_eq!(program.parse_value(), Some((100, None)));
+    }
+
+    #[test]
+    fn test_parse_value_neg() {
+        let mut program = Program::new("-100\n");
+        assert_eq!(program.parse_value(), Some((-100, None)));
+    }
+
+    #[test]
+    fn test_parse_value_indexed() {
+        let mut program = Program::new("-1,3\n");
+        assert_eq!(program.parse_value(), Some((-1, Some(reg(3)))));
+    }
+
+    #[test]
+    fn test_parse_value_invalid() {
+        let mut program = Program::new("abc\n");
+        assert_eq!(program.parse_value(), None);
+    }
+
+    #[test]
+    fn test_parse_value_empty() {
+        let mut program = Program::new("\n");
+        assert_eq!(program.parse_value(), None);
+    }
+
+    #[test]
+    fn test_parse_address() {
+        let mut program = Program::new("128\n");
+        assert_eq!(
+            program.parse_address(Field::WORD),
+            Some((128, None, Field::WORD))
+        );
+        let mut program = Program::new("128(1:3) 7");
+        assert_eq!(
+            program.parse_address(Field::WORD),
+            Some((128, None, Field::new(1, 3).unwrap()))
+        );
+        let mut program = Program::new("128,2(1:3) 7");
+        assert_eq!(
+            program.parse_address(Field::WORD),
+            Some((128, Some(reg(2)), Field::new(1, 3).unwrap()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid index register at line 0")]
+    fn test_parse_address_invalid_index() {
+        let mut program = Program::new("LDA 128,0");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Extended index register 7 in strict mode at line 0")]
+    fn test_parse_address_strict_index() {
+        let mut program = Program::new("LDA 128,7");
+        program.parse();
+    }
+
+    #[test]
+    fn test_parse_program_indexed() {
+        let indexed = |n, instruction| Instruction::Indexed(reg(n), Box::new(instruction));
+        let mut program = Program::new("LDA 100,1(1:3); ENTA 0,2; STA 200,3; IN 0,4(16); SLA 0,5");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                indexed(1, Instruction::LDA(100, Field::new(1, 3).unwrap())),
+                indexed(2, Instruction::ENTA(0)),
+                indexed(3, Instruction::STA(200, Field::WORD)),
+                indexed(4, Instruction::IN(0, 16)),
+                indexed(5, Instruction::SLA(0)),
+            ]
+        );
+        let listing: Vec<String> = program.instructions.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            listing,
+            vec![
+                "LDA 100,1(1:3)",
+                "ENTA 0,2",
+                "STA 200,3",
+                "IN 0,4(16)",
+                "SLA 0,5"
+            ]
+        );
+        for instruction in &program.instructions {
+            let word = instruction.encode().unwrap();
+            assert_eq!(Instruction::decode(word).as_ref(), Some(instruction));
+        }
+    }
+
+    #[test]
+    fn test_program_indexed() {
+        let mut mmix = MMix::new();
+        mmix.write_memory(100, &words(&[10, 20, 30])).unwrap();
+        // sum M[100..103] by walking rI1 down from 2
+        let mut program = Program::new(
+            "ENT1 2; LDA 100,1; ENT1 1; ADD 100,1; ENT1 0; ADD 100,1; ENT2 5; STA 95,2; ENTX 3,2",
+        );
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 60);
+        assert_eq!(mmix.read_memory(100, 1).unwrap(), words(&[60]));
+        assert_eq!(mmix.register(RegisterName::X), 8);
+        assert_eq!(summary.mems, 4);
+    }
+
+    #[test]
+    fn test_program_indexed_invalid_address() {
+        let mut mmix = MMix::new();
+        let mut program = Program::new("ENN1 5; LDA 4,1; HLT");
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::InvalidAddress);
+        assert_eq!(summary.pc, 1);
+    }
+
+    #[test]
+    fn test_program_address_outside_memory() {
+        for source in ["LDA 4000", "STA 4095", "ENT1 100; CMPA 3950,1"] {
+            let mut mmix = MMix::new();
+            let mut program = Program::new(source);
+            program.parse();
+            let summary = mmix.execute(&program);
+            assert_eq!(
+                summary.stop_reason,
+                StopReason::InvalidAddress,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_program_io_latency() {
+        let source = "OUT 100(18); JBUS 1(18); JRED 4(18); HLT; OUT 100(18); OUT 100(18); HLT";
+        let mut program = Program::new(source);
+        program.parse();
+
+        // transfers finish at once, so JBUS falls through and JRED jumps
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.instructions, 6);
+        assert_eq!(summary.cycles, 15);
+
+        // JBUS spins until the printer is done, and the last OUT waits for
+        // the one before it
+        let config = MachineConfig::new()
+            .with_io_latency(true)
+            .with_unit_latency(18, 100);
+        let mut mmix = MMix::with_config(config);
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.instructions, 105);
+        assert_eq!(summary.cycles, 203 + 10);
+        assert_eq!(
+            mmix.device::<LinePrinter>(18)
+                .unwrap()
+                .text()
+                .lines()
+                .count(),
+            3
+        );
+        // HLT does not wait for the last line
+        assert!(mmix.is_busy(18));
+        assert!(!mmix.is_busy(20));
+
+        // without an override the unit takes its device's latency
+        let config = MachineConfig::new().with_io_latency(true);
+        let mut mmix = MMix::with_config(config);
+        let mut program = Program::new("OUT 100(18)");
+        program.parse();
+        mmix.execute(&program);
+        assert!(mmix.is_busy(18));
+        assert!(!mmix.is_busy(17));
+    }
+
+    #[test]
+    fn test_program_io_block_outside_memory() {
+        for source in ["IN 3990(16)", "OUT 3999(18)", "ENT1 100; OUT 3900,1(17)"] {
+            let mut mmix = MMix::new();
+            let mut program = Program::new(source);
+            program.parse();
+            let summary = mmix.execute(&program);
+            assert_eq!(
+                summary.stop_reason,
+                StopReason::InvalidAddress,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid field specification at line 0")]
+    fn test_parse_address_invalid_field() {
+        let mut program = Program::new("LDA 128(4:2)");
+        program.parse();
+    }
+
+    #[test]
+    fn test_parse_program_load() {
+        let mut program = Program::new("LDA 100\nLDX 200\nLD1 400\nLD5 500\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::LDA(100, Field::WORD),
+                Instruction::LDX(200, Field::WORD),
+                Instruction::LDI(reg(1), 400, Field::WORD),
+                Instruction::LDI(reg(5), 500, Field::WORD),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_load_neg() {
+        let mut program = Program::new("LDAN 100\nLDXN 200\nLD1N 400\nLD5N 500\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::LDAN(100, Field::WORD),
+                Instruction::LDXN(200, Field::WORD),
+                Instruction::LDIN(reg(1), 400, Field::WORD),
+                Instruction::LDIN(reg(5), 500, Field::WORD),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_store() {
+        let mut program = Program::new("STA 100\nSTX 200\nSTJ 300\nST1 400\nST5 500\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::STA(100, Field::WORD),
+                Instruction::STX(200, Field::WORD),
+                Instruction::STJ(300, Field::ADDRESS),
+                Instruction::STI(reg(1), 400, Field::WORD),
+                Instruction::STI(reg(5), 500, Field::WORD),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_store_zero() {
+        let mut program = Program::new("STZ 100\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![Instruction::STZ(100, Field::WORD)]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_enter() {
+        let mut program =
+            Program::new("ENTA 100\nENTX 200\nENT1 300\nENNA 300\nENN1 400\nENN5 500\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::ENTA(100),
+                Instruction::ENTX(200),
+                Instruction::ENTI(reg(1), 300),
+                Instruction::ENNA(300),
+                Instruction::ENNI(reg(1), 400),
+                Instruction::ENNI(reg(5), 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_add() {
+        let mut program = Program::new("ADD 100\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![Instruction::ADD(100, Field::WORD)]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_sub() {
+        let mut program = Program::new("SUB 100\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![Instruction::SUB(100, Field::WORD)]
+        );
+    }
+
+    #[test]
+    fn test_program_ent_sto_a() {
+        let mut program = Program::new("ENTA 112\nSTA 200\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 112);
+        assert_eq!(i64::from(mmix.memory[200]), 112);
+    }
+
+    #[test]
+    fn test_program_ent_sto_x() {
+        let mut program = Program::new("ENTX 112\nSTX 200\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::X), 112);
+        assert_eq!(i64::from(mmix.memory[200]), 112);
+    }
+
+    #[test]
+    fn test_program_ent_sto_i() {
+        for i in 1..10 {
+            let mut program = extended_program(format!("ENT{} 112\nST{} 200\n", i, i).as_str());
+            program.parse();
+            let mut mmix = extended_mmix();
+            mmix.execute(&program);
+            assert_eq!(mmix.i[i as usize], 112);
+            assert_eq!(i64::from(mmix.memory[200]), 112);
+        }
+    }
+
+    #[test]
+    fn test_program_ent_sto_neg_a() {
+        let mut program = Program::new("ENNA 112\nSTA 200\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), -112);
+        assert_eq!(i64::from(mmix.memory[200]), -112);
+    }
+
+    #[test]
+    fn test_program_ent_sto_neg_x() {
+        let mut program = Program::new("ENNX 112\nSTX 200\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::X), -112);
+        assert_eq!(i64::from(mmix.memory[200]), -112);
+    }
+
+    #[test]
+    fn test_program_ent_sto_neg_i() {
+        for i in 1..10 {
+            let mut program = extended_program(format!("ENN{} 112\nST{} 200\n", i, i).as_str());
+            program.parse();
+            let mut mmix = extended_mmix();
+            mmix.execute(&program);
+            assert_eq!(mmix.i[i as usize], -112);
+            assert_eq!(i64::from(mmix.memory[200]), -112);
+        }
+    }
+
+    #[test]
+    fn test_program_load_a() {
+        let mut program = Program::new("LDA 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(175));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 175);
+    }
+
+    #[test]
+    fn test_program_load_x() {
+        let mut program = Program::new("LDX 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(175));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::X), 175);
+    }
+
+    #[test]
+    fn test_program_load_i() {
+        for i in 1..10 {
+            let mut program = extended_program(format!("LD{} 100\n", i).as_str());
+            program.parse();
+            let mut mmix = extended_mmix();
+            mmix.memory.set(100, Word::from(175));
+            mmix.execute(&program);
+            assert_eq!(mmix.i[i as usize], 175);
+        }
+    }
+
+    #[test]
+    fn test_program_load_neg_a() {
+        let mut program = Program::new("LDAN 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(-175));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 175);
+    }
+
+    #[test]
+    fn test_program_load_neg_x() {
+        let mut program = Program::new("LDXN 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(-175));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::X), 175);
+    }
+
+    #[test]
+    fn test_program_load_neg_i() {
+        for i in 1..10 {
+            let mut program = extended_program(format!("LD{}N 100\n", i).as_str());
+            program.parse();
+            let mut mmix = extended_mmix();
+            mmix.memory.set(100, Word::from(-175));
+            mmix.execute(&program);
+            assert_eq!(mmix.i[i as usize], 175);
+        }
+    }
+
+    #[test]
+    fn test_program_add() {
+        let mut program = Program::new("ADD 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.a = Word::from(100);
+        mmix.memory.set(100, Word::from(75));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 175);
+    }
+
+    #[test]
+    fn test_program_sub() {
+        let mut program = Program::new("SUB 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.a = Word::from(100);
+        mmix.memory.set(100, Word::from(75));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 25);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn test_program_add_overflow() {
+        let mut program = Program::new("ADD 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.a = Word::from(100);
+        mmix.memory.set(100, Word::from(MAX_WORD));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 99);
+        assert_eq!(mmix.overflow, true);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn test_program_sub_overflow() {
+        let mut program = Program::new("SUB 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.a = Word::from(-100);
+        mmix.memory.set(100, Word::from(MAX_WORD));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), -99);
+        assert_eq!(mmix.overflow, true);
+    }
+
+    #[test]
+    fn test_program_overflow_toggle_sticky() {
+        let mut program = Program::new("ADD 100; ENTA 1; ADD 101\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.a = Word::from(1);
+        mmix.memory.set(100, Word::from(MAX_WORD));
+        mmix.memory.set(101, Word::from(1));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 2);
+        assert!(mmix.overflow);
+    }
+
+    #[test]
+    fn test_program_inc_dec() {
+        let mut program = Program::new(
+            "ENTA 5; INCA 3; DECA 10; ENTX 7; DECX 7; INCX 0; ENT1 2; INC1 3; DEC1 1; ENNA 0; INCA 0",
+        );
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::X), 0);
+        assert!(!mmix.x.negative);
+        assert_eq!(mmix.i[1], 4);
+        // a zero sum keeps the sign of the register
+        assert_eq!(
+            mmix.register_word(RegisterName::A),
+            Word::default().negated()
+        );
+        assert!(!mmix.overflow);
+        let mut program = Program::new("ENTA 5; INCA 3; DECA 10");
+        program.parse();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), -2);
+    }
+
+    #[test]
+    fn test_program_inc_overflow() {
+        let mut program = Program::new("INCA 1; DECX 2");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.a = Word::from(MAX_WORD);
+        mmix.x = Word::from(-MAX_WORD);
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 0);
+        assert_eq!(mmix.register(RegisterName::X), -1);
+        assert!(mmix.overflow);
+    }
+
+    #[test]
+    fn test_program_optimize_negative_zero() {
+        let run = |optimize: bool| {
+            let mut program = Program::new("ENNA 0; STA 100; LDA 200; HLT");
+            program.parse_with(optimize);
+            let mut mmix = MMix::new();
+            mmix.execute(&program);
+            mmix.peek_word(100).unwrap()
+        };
+        assert_eq!(run(false), Word::default().negated());
+        assert_eq!(run(true), run(false));
+    }
+
+    #[test]
+    fn test_program_relaxed_overflow() {
+        let mut program = Program::new("ADD 100; ENTA 1; ADD 101\n");
+        program.parse();
+        let mut mmix = MMix::with_config(MachineConfig::new().with_arithmetic(Arithmetic::Relaxed));
+        mmix.a = Word::from(1);
+        mmix.memory.set(100, Word::from(MAX_WORD));
+        mmix.memory.set(101, Word::from(1));
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 2);
+        assert!(!mmix.overflow);
+        assert_eq!(mmix.arith_events().len(), 1);
+    }
+
+    #[test]
+    fn test_program_negative_zero() {
+        let mut program = Program::new("ENNA 0; STA 100; LDX 100; ENTA 5; STA 101(0:0)\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        let zero = Word::default().negated();
+        assert_eq!(mmix.peek_word(100), Some(zero));
+        assert_eq!(mmix.register_word(RegisterName::X), zero);
+        assert_eq!(mmix.register(RegisterName::X), 0);
+        assert_eq!(mmix.peek_word(101), Some(Word::default()));
+    }
+
+    #[test]
+    fn test_program_load_sign_field() {
+        let mut program = Program::new("LDA 100(0:0); LDXN 101(0:0); CMPA 101\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(-12345));
+        mmix.execute(&program);
+        assert_eq!(
+            mmix.register_word(RegisterName::A),
+            Word::default().negated()
+        );
+        assert_eq!(
+            mmix.register_word(RegisterName::X),
+            Word::default().negated()
+        );
+        // +0 and -0 compare equal
+        assert_eq!(mmix.comparison_indicator(), Some(Ordering::Equal));
+    }
+
+    #[test]
+    fn test_program_add_keeps_sign_of_zero() {
+        let mut program = Program::new("ENNA 5; ADD 100\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(5));
+        mmix.execute(&program);
+        assert_eq!(
+            mmix.register_word(RegisterName::A),
+            Word::default().negated()
+        );
+    }
+
+    #[test]
+    fn test_program_byte_size() {
+        let config = MachineConfig::new().with_byte_size(100);
+        let mut program = Program::with_config("ENTA 99; SLA 1; ADD 100; STA 101; HLT\n", &config);
+        program.parse();
+        let mut mmix = MMix::with_config(config);
+        mmix.write_memory(100, &[Word::from_value(1, 100)]).unwrap();
+        mmix.load_program_into_memory(&program, 0).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 9901);
+        assert_eq!(mmix.peek_word(101).unwrap().bytes, [0, 0, 0, 99, 1]);
+    }
+
+    #[test]
+    fn test_card_deck_load_and_punch() {
+        let deck = CardDeck::parse("LOADER CARD\nSECOND\n");
+        let mut mmix = MMix::new();
+        mmix.load_deck(&deck);
+        assert!(mmix.read_card(100).unwrap());
+        assert_eq!(mmix.memory[100], deck.cards()[0].to_words()[0]);
+        mmix.punch_card(100).unwrap();
+        assert!(mmix.read_card(100).unwrap());
+        mmix.punch_card(100).unwrap();
+        assert!(!mmix.read_card(100).unwrap());
+        assert_eq!(mmix.punched_deck(), &deck);
+    }
+
+    #[test]
+    fn test_invalid_unit() {
+        let mut mmix = MMix::new();
+        assert_eq!(mmix.output(25, 0), Err(AddressError::InvalidUnit(25)));
+        assert_eq!(mmix.input(UNITS, 0), Err(AddressError::InvalidUnit(UNITS)));
+        assert_eq!(
+            AddressError::InvalidUnit(25).to_string(),
+            "unit 25 does not exist"
+        );
+    }
+
+    #[test]
+    fn test_parse_program_io() {
+        let mut program = Program::new("IN 100(16); OUT 100(18); IOC 0(18); IOC -2(3); IN 200");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::IN(100, 16),
+                Instruction::OUT(100, 18),
+                Instruction::IOC(0, 18),
+                Instruction::IOC(-2, 3),
+                Instruction::IN(200, 0),
+            ]
+        );
+        assert_eq!(program.instructions[3].to_string(), "IOC -2(3)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid unit at line 0")]
+    fn test_parse_program_invalid_unit() {
+        let mut program = Program::new("OUT 100(20)");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_io() {
+        let mut mmix = MMix::new();
+        mmix.load_deck(&CardDeck::parse("HELLO\nWORLD\n"));
+        let mut program = Program::new(
+            "IN 100(16); OUT 100(18); IN 100(16); OUT 100(17); OUT 100(1); IOC 0(1); IN 300(1)",
+        );
+        program.parse();
+        mmix.execute(&program);
+        let printer = mmix.device::<LinePrinter>(LINE_PRINTER).unwrap();
+        assert_eq!(printer.text(), "HELLO\n");
+        assert_eq!(mmix.punched_deck().to_text(), "WORLD\n");
+        assert_eq!(
+            mmix.read_memory(300, 1).unwrap(),
+            mmix.read_memory(100, 1).unwrap()
+        );
+        assert_eq!(mmix.opcode_stats().count(OpcodeFamily::InputOutput), 7);
+    }
+
+    #[test]
+    fn test_program_io_journal() {
+        let mut mmix = MMix::new();
+        mmix.load_deck(&CardDeck::parse("HELLO\n"));
+        let mut program = Program::new("IN 100(16); OUT 100(18); IOC 0(18); IN 200(16)");
+        program.parse();
+        let summary = mmix.execute(&program);
+        let card = mmix.read_memory(100, 16).unwrap();
+        let line = mmix.read_memory(100, 24).unwrap();
+        assert_eq!(
+            summary.io,
+            vec![
+                IoEvent {
+                    pc: 0,
+                    unit: CARD_READER,
+                    kind: IoKind::Input,
+                    address: 100,
+                    words: card,
+                },
+                IoEvent {
+                    pc: 1,
+                    unit: LINE_PRINTER,
+                    kind: IoKind::Output,
+                    address: 100,
+                    words: line,
+                },
+                IoEvent {
+                    pc: 2,
+                    unit: LINE_PRINTER,
+                    kind: IoKind::Control,
+                    address: 0,
+                    words: vec![],
+                },
+                IoEvent {
+                    pc: 3,
+                    unit: CARD_READER,
+                    kind: IoKind::Input,
+                    address: 200,
+                    words: vec![],
+                },
+            ]
+        );
+        // each run has a journal of its own
+        let summary = mmix.execute(&Program::new("HLT"));
+        assert!(summary.io.is_empty());
+    }
+
+    #[test]
+    fn test_parse_program_shift_move() {
+        let mut program = Program::new("SLA 1; SRA 2; SLAX 3; SRAX 4; SLC 5; SRC 6; MOVE 100(3)");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::SLA(1),
+                Instruction::SRA(2),
+                Instruction::SLAX(3),
+                Instruction::SRAX(4),
+                Instruction::SLC(5),
+                Instruction::SRC(6),
+                Instruction::MOVE(100, 3),
+            ]
+        );
+        assert_eq!(program.instructions[6].to_string(), "MOVE 100(3)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid instruction at line 0")]
+    fn test_parse_program_negative_shift() {
+        let mut program = Program::new("SLA -1");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_shift() {
+        let mut mmix = MMix::new();
+        let mut program = Program::new("ENTA 1; ENNX 2; SLAX 4; SRC 1; SLA 1");
+        program.parse();
+        mmix.execute(&program);
+        // + 00 00 00 00 01, - 00 00 00 00 02 becomes + 01 00 00 00 00,
+        // - 02 00 00 00 00, then + 00 01 00 00 00, - 00 02 00 00 00
+        assert_eq!(mmix.register(RegisterName::A), BYTE_SIZE.pow(4));
+        assert_eq!(mmix.register(RegisterName::X), -2 * BYTE_SIZE.pow(3));
+    }
+
+    #[test]
+    fn test_program_binary_shift() {
+        let config = MachineConfig::new().with_instruction_set(InstructionSet::Extended);
+        let mut program = Program::with_config("ENTA 5; ENNX 1; SLB 30; SRB 1", &config);
+        program.parse();
+        let mut mmix = MMix::with_config(config);
+        mmix.execute(&program);
+        // 5 * 2^30 + 1 loses its top bits and keeps both signs
+        assert_eq!(mmix.register_word(RegisterName::A), Word::from(0));
+        assert_eq!(mmix.register(RegisterName::X), -(1 << 29));
+        assert!(mmix.register_word(RegisterName::X).negative);
+    }
+
+    #[test]
+    fn test_program_parity_jumps() {
+        let config = MachineConfig::new().with_instruction_set(InstructionSet::Extended);
+        let mut program = Program::with_config(
+            "ENTA 3; JAE 3; JAO 4; HLT; ENTX -4; JXO 3; JXE 8; HLT; ENTA 7; HLT",
+            &config,
+        );
+        program.parse();
+        let mut mmix = MMix::with_config(config);
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 7);
+    }
+
+    #[test]
+    fn test_program_binary_byte_size() {
+        let config = MachineConfig::new().with_instruction_set(InstructionSet::Extended);
+        let mut program = Program::with_config("SLB 1", &config);
+        program.parse();
+        let mut mmix = MMix::with_config(config.with_byte_size(100));
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::InvalidInstruction);
+
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::ExtendedInstruction);
+    }
+
+    #[test]
+    #[should_panic(expected = "Binary instruction JXE with byte size 100 at line 0")]
+    fn test_parse_program_binary_byte_size() {
+        let config = MachineConfig::new()
+            .with_instruction_set(InstructionSet::Extended)
+            .with_byte_size(100);
+        let mut program = Program::with_config("JXE 5", &config);
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Extended instruction SRB in strict mode at line 0")]
+    fn test_parse_program_binary_strict() {
+        let mut program = Program::new("SRB 5");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_move() {
+        let mut mmix = MMix::new();
+        mmix.write_memory(100, &words(&[1, 2, 3])).unwrap();
+        let mut program = Program::new("ENT1 200; MOVE 100(3); ENT1 101; MOVE 100(2)");
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(mmix.read_memory(200, 3).unwrap(), words(&[1, 2, 3]));
+        // the overlapping move copies M[100] forward one word at a time
+        assert_eq!(mmix.read_memory(100, 4).unwrap(), words(&[1, 1, 1, 0]));
+        assert_eq!(mmix.i[1], 103);
+        assert_eq!(summary.mems, 10);
+        assert_eq!(summary.cycles, 1 + 7 + 1 + 5);
+    }
+
+    #[test]
+    fn test_program_move_outside_memory() {
+        for source in ["ENN1 5; MOVE 0(3)", "ENT1 3999; MOVE 0(3)", "MOVE 3999(2)"] {
+            let mut mmix = MMix::new();
+            let mut program = Program::new(source);
+            program.parse();
+            let summary = mmix.execute(&program);
+            assert_eq!(
+                summary.stop_reason,
+                StopReason::InvalidAddress,
+                "{}",
+                source
+            );
+        }
+        // moving nothing never looks at rI1
+        let mut mmix = MMix::new();
+        let mut program = Program::new("ENN1 5; MOVE 0(0)");
+        program.parse();
+        assert_eq!(mmix.execute(&program).stop_reason, StopReason::EndOfProgram);
+    }
+
+    #[test]
+    fn test_parse_program_jumps() {
+        let mut program = Program::new(
+            "JMP 1; JSJ 2; JOV 3; JNOV 4; JL 5; JGE 6; JAN 7; JXNZ 8; J3NP 9,2; JBUS 0(16)",
+        );
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::JMP(1),
+                Instruction::JSJ(2),
+                Instruction::JOV(3),
+                Instruction::JNOV(4),
+                Instruction::JL(5),
+                Instruction::JGE(6),
+                Instruction::JA(RegisterTest::Negative, 7),
+                Instruction::JX(RegisterTest::NonZero, 8),
+                Instruction::Indexed(
+                    reg(2),
+                    Box::new(Instruction::JI(reg(3), RegisterTest::NonPositive, 9))
+                ),
+                Instruction::JBUS(0, 16),
+            ]
+        );
+        let listing: Vec<String> = program.instructions.iter().map(|i| i.to_string()).collect();
+        assert_eq!(listing[7], "JXNZ 8");
+        assert_eq!(listing[8], "J3NP 9,2");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown instruction at line 0")]
+    fn test_parse_program_unknown_jump() {
+        let mut program = Program::new("JAQ 5");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Extended instruction J7N in strict mode at line 0")]
+    fn test_parse_program_strict_jump() {
+        let mut program = Program::new("J7N 5");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_loop() {
+        let mut mmix = MMix::new();
+        mmix.write_memory(100, &words(&[10, 20, 30])).unwrap();
+        // sum M[100..103], counting rI1 down from 2
+        let mut program = Program::new("ENTA 0; ENT1 2; ADD 100,1; ENT1 -1,1; J1NN 2; HLT");
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 60);
+        assert_eq!(mmix.j, 5);
+    }
+
+    #[test]
+    fn test_parse_program_labels() {
+        let mut program =
+            Program::new("START ENTA 0\nLOOP ADD DATA,1\n JMP DONE # forward\nDATA NOP\nDONE HLT");
+        program.parse();
+        assert_eq!(
+            program.instructions(),
+            &[
+                Instruction::ENTA(0),
+                Instruction::Indexed(reg(1), Box::new(Instruction::ADD(3, Field::WORD))),
+                Instruction::JMP(4),
+                Instruction::NOP,
+                Instruction::HLT,
+            ]
+        );
+        let labels: Vec<_> = program
+            .labels()
+            .iter()
+            .map(|(l, &a)| (l.as_str(), a))
+            .collect();
+        assert_eq!(
+            labels,
+            [("DATA", 3), ("DONE", 4), ("LOOP", 1), ("START", 0)]
+        );
+    }
+
+    #[test]
+    fn test_program_labels() {
+        let mut mmix = MMix::new();
+        mmix.write_memory(100, &words(&[10, 20, 30])).unwrap();
+        let mut program = Program::new(
+            "      ENTA 0; ENT1 2\nLOOP  ADD 100,1; ENT1 -1,1\n      J1NN LOOP\n      HLT",
+        );
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 60);
+    }
+
+    #[test]
+    fn test_program_labels_at_origin() {
+        let mut mmix = MMix::new();
+        let mut program = Program::new("ENT1 3\nLOOP ENTA 1\nJMP DONE\nENTX LOOP\nDONE HLT");
+        program.parse();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.pc, 104);
+        assert_eq!(mmix.register(RegisterName::X), 0);
+        assert_eq!(mmix.j, 103);
+        assert_eq!(mmix.peek_instruction(103), Some(Instruction::ENTX(101)));
+        // a numeric address that is not a jump stays where it is
+        assert_eq!(
+            mmix.peek_instruction(100),
+            Some(Instruction::ENTI(reg(1), 3))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate label LOOP at line 1")]
+    fn test_parse_program_duplicate_label() {
+        let mut program = Program::new("LOOP NOP\nLOOP HLT");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Undefined label LOOP at line 1")]
+    fn test_parse_program_undefined_label() {
+        let mut program = Program::new("ENTA 1\nJMP LOOP\nHLT");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_equ() {
+        let source = "SIZE EQU 3\nBUF  EQU 100\nHALF EQU SIZE*4/2\nEND  EQU *+2\n     ENTA HALF+1\n     STA BUF+SIZE-1\n     HLT";
+        let mut program = Program::new(source);
+        program.parse();
+        assert_eq!(program.equates()["SIZE"], 3);
+        assert_eq!(program.equates()["HALF"], 6);
+        assert_eq!(program.labels()["END"], 2);
+        assert!(!program.labels().contains_key("SIZE"));
+        assert_eq!(
+            program.instructions()[1],
+            Instruction::STA(102, Field::WORD)
+        );
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(i64::from(mmix.memory[102]), 7);
+    }
+
+    #[test]
+    fn test_program_expressions() {
+        let mut program = Program::new(
+            "SIX EQU 6\nENTA 1+2*3\nENTX -7/2\nENT1 1:3\nENT2 1//2\nENT3 2*SIX-1\nHLT",
+        );
+        program.parse();
+        assert_eq!(
+            program.instructions()[..5],
+            [
+                Instruction::ENTA(9),
+                Instruction::ENTX(-3),
+                Instruction::ENTI(reg(1), 11),
+                Instruction::ENTI(reg(2), 64i64.pow(5) / 2),
+                Instruction::ENTI(reg(3), 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_program_location_counter() {
+        let mut program =
+            Program::new("ENTA *\nJMP *+2\nHLT\nENTX DONE-*\nJMP DONE+1\nDONE HLT\nHLT");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.pc, 106);
+        assert_eq!(mmix.register(RegisterName::A), 100);
+        // the difference of two addresses does not move with the program
+        assert_eq!(mmix.register(RegisterName::X), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid relocation at line 1")]
+    fn test_program_expression_relocation() {
+        let mut program = Program::new("LOOP NOP\nENTA LOOP+*\nHLT");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid relocation at line 1")]
+    fn test_program_forward_relocation() {
+        let mut program = Program::new("NOP\nENTA -LATER\nLATER HLT");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Undefined label LATER at line 0")]
+    fn test_program_equ_forward_reference() {
+        let mut program = Program::new("SIZE EQU LATER+1\nLATER HLT");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid expression at line 0")]
+    fn test_program_invalid_expression() {
+        let mut program = Program::new("ENTA 1+\nHLT");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_jumps() {
+        let run = |source: &str| {
+            let mut mmix = MMix::new();
+            let mut program = Program::new(source);
+            program.parse();
+            mmix.execute(&program);
+            mmix
+        };
+        // a jump not taken falls through to ENTX 1
+        let taken =
+            |source: &str| run(&format!("{}; ENTX 1; HLT; ENTX 2; HLT", source)).x == Word::from(2);
+        assert!(taken("JMP 3"));
+        assert!(!taken("JE 3"));
+        assert!(!taken("JNE 3"));
+        assert!(taken("ENTA 1; CMPA 100; JG 5"));
+        assert!(taken("ENTA 1; CMPA 100; JNE 5"));
+        assert!(!taken("ENTA 1; CMPA 100; JLE 5"));
+        assert!(taken("ENNA 1; JANP 4"));
+        assert!(!taken("ENNA 1; JAP 4"));
+        assert!(taken("ENT4 0; J4Z 4"));
+        assert!(taken("JRED 3(18)"));
+        assert!(!taken("JBUS 3(18)"));
+        assert!(taken("JNOV 3"));
+        assert!(!taken("JOV 3"));
+        // JSJ leaves rJ alone
+        assert_eq!(run("JSJ 3; HLT; HLT; HLT").j, 0);
+        assert_eq!(run("NOP; JMP 3; HLT; HLT").j, 2);
+    }
+
+    #[test]
+    fn test_program_jump_overflow() {
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(MAX_WORD));
+        let mut program = Program::new("LDA 100; ADD 100; JOV 4; HLT; JOV 6; ENTX 1; HLT");
+        program.parse();
+        mmix.execute(&program);
+        // the first JOV jumps and turns the toggle off, so the second falls
+        // through
+        assert!(!mmix.overflow);
+        assert_eq!(mmix.register(RegisterName::X), 1);
+    }
+
+    #[test]
+    fn test_attach_device() {
+        let mut mmix = MMix::new();
+        let mut disk = Disk::new();
+        disk.set_block(5, &words(&[42]));
+        mmix.attach(8, disk);
+        let mut program = Program::new("ENTX 5; IN 100(8); ENTX 6; OUT 100(8)");
+        program.parse();
+        mmix.execute(&program);
+        assert_eq!(mmix.read_memory(100, 1).unwrap(), words(&[42]));
+        assert_eq!(
+            mmix.device::<Disk>(8).unwrap().block(6).unwrap()[0],
+            Word::from(42)
+        );
+        assert!(mmix.device::<Tape>(8).is_none());
+    }
+
+    #[test]
+    fn test_frame_buffer_rows() {
+        let mut mmix = MMix::new();
+        mmix.attach(0, FrameBuffer::new(2, 2));
+        // blue, green, red and white pixels
+        mmix.write_memory(100, &words(&[63, 63 * 64, 63 * 64 * 64, 262143]))
+            .unwrap();
+        let mut program = Program::new("OUT 100(0); OUT 102(0); IOC 0(0); OUT 102(0); HLT");
+        program.parse();
+        mmix.execute(&program);
+        let screen = mmix.device::<FrameBuffer>(0).unwrap();
+        assert_eq!(screen.row(), 1);
+        assert_eq!(screen.pixel(0, 0), [63, 0, 0]);
+        assert_eq!(screen.pixel(1, 0), [63, 63, 63]);
+        assert_eq!(screen.pixel(1, 1), [63, 63, 63]);
+        assert_eq!(&screen.to_ppm()[..10], b"P6\n2 2\n63\n");
+    }
+
+    #[test]
+    fn test_parse_program_separators() {
+        let mut program = Program::new("ENTA 5; STA 100; HLT");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::ENTA(5),
+                Instruction::STA(100, Field::WORD),
+                Instruction::HLT
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_comments() {
+        let mut program = Program::new("# setup\nENTA 5 # five\nSTA 100;# store\nHLT\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::ENTA(5),
+                Instruction::STA(100, Field::WORD),
+                Instruction::HLT
+            ]
+        );
+        assert_eq!(program.line, 4);
+    }
+
+    #[test]
+    fn test_parse_program_remarks() {
+        let mut program = Program::new("STA 100 store it; HLT   Stop.\nENTA 5 # five; six\n");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::STA(100, Field::WORD),
+                Instruction::HLT,
+                Instruction::ENTA(5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_program_taocp_listing() {
+        let listing = "\
+* SUM OF THREE WORDS
+START     ENTA 0              Clear rA.
+          ENT1 2              Start at the last word.
+LOOP      ADD  100,1          rA <- rA + X[i].
+          ENT1 -1,1           Move down one.
+          J1NN LOOP           Repeat until i < 0.
+*
+          HLT                 Done.
+";
+        let mut mmix = MMix::new();
+        mmix.write_memory(100, &words(&[10, 20, 30])).unwrap();
+        let mut program = Program::new(listing);
+        program.parse();
+        assert_eq!(program.instructions().len(), 6);
+        assert_eq!(program.labels().get("LOOP"), Some(&2));
+        assert_eq!(mmix.execute(&program).stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 60);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown instruction at line 2")]
+    fn test_parse_program_comment_line_number() {
+        let mut program = Program::new("ENTA 5 # five\nSTA 100; HLT\nNOPE 1\n");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_halt() {
+        let mut program = Program::new("ENTA 5; HLT; STA 100");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::A), 5);
+        assert_eq!(i64::from(mmix.memory[100]), 0);
+    }
+
+    #[test]
+    fn test_mmix_with_config() {
+        let config = MachineConfig::new()
+            .with_memory_size(200)
+            .with_index_registers(6);
+        let mmix = MMix::with_config(config.clone());
+        assert_eq!(mmix.memory.len(), 200);
+        assert_eq!(mmix.config(), &config);
+    }
+
+    #[test]
+    fn test_index_register_count() {
+        let config = MachineConfig::new()
+            .with_instruction_set(InstructionSet::Extended)
+            .with_index_registers(6);
+        let mut mmix = MMix::with_config(config.clone());
+        let mut program = Program::with_config("ENT6 5; ST6 100", &config);
+        program.parse();
+        mmix.execute(&program);
+        assert_eq!(mmix.register(RegisterName::I(reg(6))), 5);
+        // a program parsed for a bigger machine stops at the missing register
+        let mut program = extended_program("ENTA 1; ENT7 5");
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::InvalidInstruction);
+        assert_eq!(summary.pc, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index register 5 is not configured at line 1")]
+    fn test_parse_program_unconfigured_register() {
+        let config = MachineConfig::new().with_index_registers(4);
+        let mut program = Program::with_config("ENT4 1\nLDA 100,5", &config);
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_execute_from_memory() {
+        let mut program = Program::new("ENTA 112; STA 200; HLT");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        mmix.execute_from_memory(100);
+        assert_eq!(mmix.register(RegisterName::A), 112);
+        assert_eq!(i64::from(mmix.memory[200]), 112);
+    }
+
+    #[test]
+    fn test_program_self_modifying() {
+        // STZ 2 overwrites the ENTA 7 that follows it with a NOP
+        let mut program = Program::new("ENTA 1; STZ 2; ENTA 7; HLT");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 0).unwrap();
+        mmix.execute_from_memory(0);
+        assert_eq!(mmix.register(RegisterName::A), 1);
+        assert_eq!(i64::from(mmix.memory[2]), 0);
+    }
+
+    #[test]
+    fn test_program_run_summary() {
+        let mut program = Program::new("ENTA 1; STA 100; HLT; ENTA 2");
+        program.parse();
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.instructions, 3);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+
+        let mut program = Program::new("ENTA 1; STA 100");
+        program.parse();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.instructions, 2);
+        assert_eq!(summary.stop_reason, StopReason::EndOfProgram);
+    }
+
+    #[test]
+    fn test_program_arith_events() {
+        let mut program = Program::new("LDA 100; ADD 100; STA 101; SUB 102; ADD 100");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(MAX_WORD));
+        mmix.memory.set(102, Word::from(1));
+        mmix.execute(&program);
+        assert_eq!(
+            mmix.arith_events(),
+            &[
+                ArithEvent {
+                    pc: 1,
+                    kind: ArithEventKind::Overflow
+                },
+                ArithEvent {
+                    pc: 4,
+                    kind: ArithEventKind::Overflow
+                },
+            ]
+        );
+        assert_eq!(mmix.arith_events()[1].to_string(), "overflow at pc=4");
+        mmix.clear_arith_events();
+        assert!(mmix.arith_events().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Extended instruction LD7 in strict mode at line 1")]
+    fn test_parse_program_strict() {
+        let mut program = Program::new("LD6 100\nLD7 100\n");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid instruction at line 0")]
+    fn test_parse_program_two_digit_enter() {
+        let mut program = extended_program("ENT10 5");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid instruction at line 0")]
+    fn test_parse_program_two_digit_enter_negative() {
+        let mut program = extended_program("ENN10 5");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid index register at line 1")]
+    fn test_parse_program_two_digit_load() {
+        let mut program = extended_program("LD1 100\nLD10 100\n");
+        program.parse();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid index register at line 0")]
+    fn test_parse_program_two_digit_store() {
+        let mut program = extended_program("ST10 100");
+        program.parse();
+    }
+
+    #[test]
+    fn test_program_strict_execute_extended() {
+        let mut program = extended_program("ENT1 1; ENT7 1\n");
+        program.parse();
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::ExtendedInstruction);
+        assert_eq!(summary.pc, 1);
+        assert_eq!(mmix.i[7], 0);
+    }
+
+    #[test]
+    fn test_program_extended() {
+        let mut program = extended_program("ENT9 5; ST9 100\n");
+        program.parse();
+        let mut mmix = extended_mmix();
+        mmix.execute(&program);
+        assert_eq!(i64::from(mmix.memory[100]), 5);
+    }
+
+    #[test]
+    fn test_memory_fill() {
+        let mut mmix = MMix::new();
+        mmix.fill(100, 10, Word::from(7)).unwrap();
+        assert_eq!(i64::from(mmix.memory[99]), 0);
+        assert!(mmix
+            .memory
+            .read(100, 10)
+            .iter()
+            .all(|&w| w == Word::from(7)));
+        assert_eq!(i64::from(mmix.memory[110]), 0);
+    }
+
+    #[test]
+    fn test_memory_copy_overlapping() {
+        let mut mmix = MMix::new();
+        for n in 0..5 {
+            mmix.memory.set(100 + n, Word::from(n as i64 + 1));
         }
+        mmix.copy(102, 100, 5).unwrap();
+        assert_eq!(mmix.memory.read(100, 7), words(&[1, 2, 1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_memory_fill_out_of_range() {
+        let mut mmix = MMix::new();
+        assert_eq!(
+            mmix.fill(3999, 2, Word::from(1)),
+            Err(AddressError::OutsideMemory { addr: 3999, len: 2 })
+        );
+        assert_eq!(mmix.peek_word(3999), Some(Word::default()));
+    }
+
+    #[test]
+    fn test_instruction_display() {
+        let mut program = Program::new("LD1N 5; ENN2 -3; ST4 7; ENTA -1; HLT");
+        program.parse();
+        let listing: Vec<String> = program.instructions.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            listing,
+            vec!["LD1N 5", "ENN2 -3", "ST4 7", "ENTA -1", "HLT"]
+        );
+    }
+
+    #[test]
+    fn test_parse_optimized() {
+        let mut program = Program::new("ENTA 0; STA 100; NOP; LDA 200; STA 201");
+        let report = program.parse_with(true).unwrap();
+        assert_eq!(report.saved(), 2);
+        assert_eq!(report.after, vec!["STZ 100", "LDA 200", "STA 201"]);
+
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(9));
+        mmix.memory.set(200, Word::from(5));
+        mmix.execute(&program);
+        assert_eq!(i64::from(mmix.memory[100]), 0);
+        assert_eq!(i64::from(mmix.memory[201]), 5);
+
+        let mut program = Program::new("ENTA 0; STA 100");
+        assert!(program.parse_with(false).is_none());
+        assert_eq!(program.instructions.len(), 2);
+    }
+
+    #[test]
+    fn test_program_periodic_callback() {
+        let mut program = Program::new("ENTA 1; ENTA 2; ENTA 3; ENTA 4; ENTA 5");
+        program.parse();
+        let mut seen = Vec::new();
+        let mut config = RunConfig::new().periodic(2, |mmix, count| {
+            seen.push((count, mmix.register(RegisterName::A)));
+            ControlFlow::Continue(())
+        });
+        let mut mmix = MMix::new();
+        let summary = mmix.execute_with(&program, &mut config);
+        drop(config);
+        assert_eq!(summary.stop_reason, StopReason::EndOfProgram);
+        assert_eq!(seen, vec![(2, 2), (4, 4)]);
+    }
+
+    #[test]
+    fn test_program_periodic_break() {
+        let mut program = Program::new("ENTA 1; ENTA 2; ENTA 3; ENTA 4; ENTA 5");
+        program.parse();
+        let mut config = RunConfig::new().periodic(3, |_, _| ControlFlow::Break(()));
+        let mut mmix = MMix::new();
+        let summary = mmix.execute_with(&program, &mut config);
+        assert_eq!(summary.stop_reason, StopReason::Stopped);
+        assert_eq!(summary.instructions, 3);
+        assert_eq!(mmix.register(RegisterName::A), 3);
+    }
+
+    #[test]
+    fn test_program_cancelled() {
+        let mut program = Program::new("ENTA 1; ENTA 2");
+        program.parse();
+        let token = AtomicBool::new(true);
+        let mut mmix = MMix::new();
+        let summary = mmix.execute_cancellable(&program, &token);
+        assert_eq!(summary.stop_reason, StopReason::Cancelled);
+        assert_eq!(summary.instructions, 0);
+        assert_eq!(mmix.register(RegisterName::A), 0);
+    }
+
+    #[test]
+    fn test_program_cancelled_from_callback() {
+        use std::sync::atomic::Ordering;
+
+        let mut program = Program::new("ENTA 1; ENTA 2; ENTA 3; ENTA 4");
+        program.parse();
+        let token = AtomicBool::new(false);
+        let mut config = RunConfig::new().cancel_token(&token).periodic(2, |_, _| {
+            token.store(true, Ordering::Relaxed);
+            ControlFlow::Continue(())
+        });
+        let mut mmix = MMix::new();
+        let summary = mmix.execute_with(&program, &mut config);
+        assert_eq!(summary.stop_reason, StopReason::Cancelled);
+        assert_eq!(summary.instructions, 2);
+        assert_eq!(mmix.register(RegisterName::A), 2);
+    }
+
+    #[test]
+    fn test_program_entry_point() {
+        let mut program = Program::new("ENTA 3; STA 50; HLT");
+        program.parse();
+        let mut mmix = MMix::new();
+        assert_eq!(mmix.entry_point(), None);
+        mmix.load_program_into_memory(&program, 200).unwrap();
+        assert_eq!(mmix.entry_point(), Some(200));
+        assert!(mmix.is_loaded_code(202));
+        assert!(!mmix.is_loaded_code(203));
+        mmix.execute_loaded();
+        assert_eq!(i64::from(mmix.memory[50]), 3);
+    }
+
+    #[test]
+    fn test_load_code_outside_memory() {
+        let mut mmix = MMix::new();
+        let words = words(&[1, 2]);
+        assert_eq!(
+            mmix.load_code(3999, &words),
+            Err(AddressError::OutsideMemory { addr: 3999, len: 2 })
+        );
+        assert!(!mmix.is_loaded_code(3999));
+        assert_eq!(mmix.entry_point(), None);
+        mmix.load_code(3998, &words).unwrap();
+        assert!(mmix.is_loaded_code(3999));
+    }
+
+    #[test]
+    fn test_execute_nothing_loaded() {
+        let mut mmix = MMix::new();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::NothingLoaded);
+        assert_eq!(summary.instructions, 0);
+        assert_eq!(summary.stop_reason.to_string(), "nothing loaded");
+    }
+
+    #[test]
+    fn test_program_check_loaded_code() {
+        let mut program = Program::new("ENTA 3; STA 50");
+        program.parse();
+        let mut mmix = MMix::with_config(MachineConfig::new().with_check_loaded_code(true));
+        mmix.load_program_into_memory(&program, 200).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::OutsideLoadedCode);
+        assert_eq!(summary.pc, 202);
+    }
+
+    #[test]
+    fn test_program_uninitialized_memory() {
+        let mut program = Program::new("ENTA 3; STA 50");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 200).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::UninitializedMemory);
+        assert_eq!(summary.pc, 202);
+        assert_eq!(summary.instructions, 2);
+    }
+
+    #[test]
+    fn test_set_pc() {
+        let mut program = Program::new("HLT");
+        program.parse();
+        let mut mmix = MMix::new();
+        assert_eq!(mmix.set_pc(3999), Ok(()));
+        assert_eq!(
+            mmix.set_pc(4000),
+            Err(AddressError::OutsideMemory { addr: 4000, len: 1 })
+        );
+        assert_eq!(mmix.pc(), 3999);
+        let mut mmix = MMix::with_config(MachineConfig::new().with_check_loaded_code(true));
+        mmix.load_program_into_memory(&program, 200).unwrap();
+        assert_eq!(mmix.set_pc(201), Err(AddressError::OutsideLoadedCode(201)));
+        assert_eq!(mmix.set_pc(200), Ok(()));
+    }
+
+    #[test]
+    fn test_program_checked_entry_point() {
+        let mut program = Program::new("HLT");
+        program.parse();
+        let mut mmix = MMix::with_config(MachineConfig::new().with_check_loaded_code(true));
+        mmix.load_program_into_memory(&program, 200).unwrap();
+        assert_eq!(
+            mmix.set_entry_point(100),
+            Err(AddressError::OutsideLoadedCode(100))
+        );
+        assert_eq!(
+            mmix.set_entry_point(5000),
+            Err(AddressError::OutsideMemory { addr: 5000, len: 1 })
+        );
+        assert_eq!(mmix.entry_point(), Some(200));
     }
 
     #[test]
-    fn test_parse_instruction_sta() {
-        let mut program = Program::new("STA 100\n");
-        assert_eq!(program.parse_instruction(), Some("STA".to_string()));
+    fn test_parse_program_compare() {
+        let mut program = Program::new("CMPA 100; CMPX 200; CMP3 300");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::CMPA(100, Field::WORD),
+                Instruction::CMPX(200, Field::WORD),
+                Instruction::CMPI(reg(3), 300, Field::WORD),
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_instruction_stax() {
-        let mut program = Program::new("STX 100\n");
-        assert_eq!(program.parse_instruction(), Some("STX".to_string()));
+    fn test_program_compare() {
+        let mut mmix = MMix::new();
+        assert_eq!(mmix.comparison_indicator(), None);
+        mmix.memory.set(100, Word::from(5));
+        for (program, expected) in [
+            ("ENTA 4; CMPA 100", Ordering::Less),
+            ("ENTX 5; CMPX 100", Ordering::Equal),
+            ("ENT2 6; CMP2 100", Ordering::Greater),
+            ("ENNA 5; CMPA 100", Ordering::Less),
+        ] {
+            let mut program = Program::new(program);
+            program.parse();
+            mmix.execute(&program);
+            assert_eq!(mmix.comparison_indicator(), Some(expected));
+        }
     }
 
     #[test]
-    fn test_parse_instruction_sti() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("ST{} 100\n", i).as_str());
-            assert_eq!(program.parse_instruction(), Some(format!("ST{}", i)));
-        }
+    fn test_parse_field_spec() {
+        let mut program = Program::new("LDA 100(1:3); STJ 200; ST2 300(4:5); CMPX 400(0:0)");
+        program.parse();
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::LDA(100, Field::new(1, 3).unwrap()),
+                Instruction::STJ(200, Field::ADDRESS),
+                Instruction::STI(reg(2), 300, Field::new(4, 5).unwrap()),
+                Instruction::CMPX(400, Field::new(0, 0).unwrap()),
+            ]
+        );
+        assert_eq!(program.instructions[0].to_string(), "LDA 100(1:3)");
+        assert_eq!(program.instructions[1].to_string(), "STJ 200");
     }
 
     #[test]
-    fn test_parse_instruction_stj() {
-        let mut program = Program::new("STJ 100\n");
-        assert_eq!(program.parse_instruction(), Some("STJ".to_string()));
+    fn test_program_field_spec() {
+        // - 01 16 03 05 04
+        let word = i64::from(Word {
+            negative: true,
+            bytes: [1, 16, 3, 5, 4],
+        });
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(word));
+        let mut program =
+            Program::new("LDA 100(0:3); LDX 100(4:4); STA 200(4:5); STX 200(0:0); CMPA 200(4:5)");
+        program.parse();
+        mmix.execute(&program);
+        assert_eq!(
+            mmix.register(RegisterName::A),
+            -(BYTE_SIZE.pow(2) + 16 * BYTE_SIZE + 3)
+        );
+        assert_eq!(mmix.register(RegisterName::X), 5);
+        assert_eq!(i64::from(mmix.memory[200]), 16 * BYTE_SIZE + 3);
+        assert_eq!(mmix.comparison_indicator(), Some(Ordering::Equal));
     }
 
     #[test]
-    fn test_parse_instruction_stz() {
-        let mut program = Program::new("STZ 100\n");
-        assert_eq!(program.parse_instruction(), Some("STZ".to_string()));
+    fn test_memory_write_read() {
+        let mut mmix = MMix::new();
+        let data: Vec<Word> = (1..=100).map(Word::from).collect();
+        mmix.write_memory(1000, &data).unwrap();
+        assert_eq!(i64::from(mmix.memory[999]), 0);
+        assert_eq!(i64::from(mmix.memory[1000]), 1);
+        assert_eq!(mmix.read_memory(1000, 100).unwrap(), data.as_slice());
+
+        let mut program = Program::new("LDA 1000; ADD 1099; STA 1100");
+        program.parse();
+        mmix.execute(&program);
+        assert_eq!(mmix.read_memory(1100, 1).unwrap(), words(&[101]));
     }
 
     #[test]
-    fn test_parse_instruction_enta() {
-        let mut program = Program::new("ENTA 100\n");
-        assert_eq!(program.parse_instruction(), Some("ENTA".to_string()));
+    fn test_memory_write_out_of_range() {
+        let mut mmix = MMix::new();
+        let error = AddressError::OutsideMemory { addr: 3999, len: 2 };
+        assert_eq!(mmix.write_memory(3999, &words(&[1, 2])), Err(error));
+        assert_eq!(mmix.read_memory(3999, 2), Err(error));
+        assert_eq!(mmix.read_text(3999, 2), Err(error));
+        assert_eq!(mmix.copy(100, 3999, 2), Err(error));
+        assert_eq!(mmix.copy(3999, 100, 2), Err(error));
+        let error = AddressError::OutsideMemory {
+            addr: 3990,
+            len: 16,
+        };
+        assert_eq!(mmix.read_card(3990), Err(error));
+        assert_eq!(mmix.punch_card(3990), Err(error));
+        assert_eq!(
+            mmix.read_memory(u64::MAX, 1),
+            Err(AddressError::OutsideMemory {
+                addr: u64::MAX,
+                len: 1
+            })
+        );
     }
 
     #[test]
-    fn test_parse_instruction_entx() {
-        let mut program = Program::new("ENTX 100\n");
-        assert_eq!(program.parse_instruction(), Some("ENTX".to_string()));
+    fn test_program_taint_tracking() {
+        let mut program = Program::new("LDA 100; ADD 101; STA 200; ENTX 3; LD1 102; STZ 201");
+        program.parse();
+        let mut mmix = MMix::with_config(MachineConfig::new().with_taint_tracking(true));
+        mmix.execute(&program);
+        assert_eq!(
+            mmix.register_origins(RegisterName::A),
+            Some(Origins::from([100, 101]))
+        );
+        assert_eq!(mmix.memory_origins(200), Some(Origins::from([100, 101])));
+        assert_eq!(mmix.register_origins(RegisterName::X), Some(Origins::new()));
+        assert_eq!(
+            mmix.register_origins(RegisterName::I(reg(1))),
+            Some(Origins::from([102]))
+        );
+        assert_eq!(mmix.memory_origins(201), Some(Origins::new()));
+        assert_eq!(mmix.memory_origins(300), Some(Origins::from([300])));
+        mmix.copy(300, 200, 1).unwrap();
+        assert_eq!(mmix.memory_origins(300), Some(Origins::from([100, 101])));
     }
 
     #[test]
-    fn test_parse_instruction_enti() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("ENT{} 100\n", i).as_str());
-            assert_eq!(program.parse_instruction(), Some(format!("ENT{}", i)));
-        }
+    fn test_program_taint_tracking_indexed() {
+        let mut program = Program::new("LD1 100; ENTA 0,1; STA 200; ENN2 5,1; ENTX 7");
+        program.parse();
+        let mut mmix = MMix::with_config(MachineConfig::new().with_taint_tracking(true));
+        mmix.execute(&program);
+        assert_eq!(mmix.memory_origins(200), Some(Origins::from([100])));
+        assert_eq!(
+            mmix.register_origins(RegisterName::I(reg(2))),
+            Some(Origins::from([100]))
+        );
+        assert_eq!(mmix.register_origins(RegisterName::X), Some(Origins::new()));
     }
 
     #[test]
-    fn test_parse_instruction_enna() {
-        let mut program = Program::new("ENNA 100\n");
-        assert_eq!(program.parse_instruction(), Some("ENNA".to_string()));
+    fn test_program_taint_tracking_disabled() {
+        let mmix = MMix::new();
+        assert_eq!(mmix.register_origins(RegisterName::A), None);
+        assert_eq!(mmix.memory_origins(0), None);
     }
 
     #[test]
-    fn test_parse_instruction_ennx() {
-        let mut program = Program::new("ENNX 100\n");
-        assert_eq!(program.parse_instruction(), Some("ENNX".to_string()));
+    fn test_clone_state() {
+        let mut program = Program::new("LDA 100; ADD 100; STA 101");
+        program.parse();
+        let mut mmix = MMix::new();
+        mmix.memory.set(100, Word::from(21));
+        let mut fork = mmix.clone_state();
+        assert!(fork.shares_memory_with(&mmix));
+        fork.execute(&program);
+        assert!(!fork.shares_memory_with(&mmix));
+        assert_eq!(i64::from(fork.memory[101]), 42);
+        assert_eq!(fork.register(RegisterName::A), 42);
+        assert_eq!(i64::from(mmix.memory[101]), 0);
+        assert_eq!(mmix.register(RegisterName::A), 0);
     }
 
     #[test]
-    fn test_parse_instruction_enni() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("ENN{} 100\n", i).as_str());
-            assert_eq!(program.parse_instruction(), Some(format!("ENN{}", i)));
-        }
+    fn test_program_invalid_instruction_word() {
+        let mut mmix = MMix::new();
+        mmix.memory
+            .set(10, Instruction::ENTA(4).to_word(DEFAULT_BYTE_SIZE).unwrap());
+        mmix.memory.set(11, Word::from(-63));
+        let summary = mmix.execute_from_memory(10);
+        assert_eq!(summary.stop_reason, StopReason::InvalidInstruction);
+        assert_eq!(summary.pc, 11);
+        assert_eq!(summary.word, Some(Word::from(-63)));
+        assert!(summary
+            .to_string()
+            .ends_with("invalid instruction at pc=11 [- 00 00 00 00 63]; rA=4 rX=0 rI1=0 rI2=0 rI3=0 rI4=0 rI5=0 rI6=0 rJ=0 OV=OFF CI=-"));
     }
+
     #[test]
-    fn test_parse_value() {
-        let mut program = Program::new("100\n");
-        assert_eq!(program.parse_value(), Some(100));
+    fn test_program_halt_summary() {
+        let mut program = Program::new("ENTA 5; CMPA 0; HLT");
+        program.parse();
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.pc, 2);
+        assert_eq!(summary.instruction, Some(Instruction::HLT));
+        assert!(summary
+            .to_string()
+            .contains("halted at pc=2 [+ 00 00 00 02 05] HLT; rA=5"));
+        let registers = summary.registers.unwrap();
+        assert_eq!(registers.a, 5);
+        assert_eq!(registers.comparison, Some(Ordering::Greater));
+        assert!(registers.to_string().ends_with("CI=G"));
     }
 
     #[test]
-    fn test_parse_value_neg() {
-        let mut program = Program::new("-100\n");
-        assert_eq!(program.parse_value(), Some(-100));
+    fn test_asm_line() {
+        let mut mmix = MMix::new();
+        mmix.set_entry_point(100).unwrap();
+        mmix.pc = 100;
+        let summary = mmix.asm_line("ENTA 42").unwrap();
+        assert_eq!(summary.instructions, 1);
+        assert_eq!(summary.stop_reason, StopReason::EndOfProgram);
+        assert_eq!(mmix.register(RegisterName::A), 42);
+        assert_eq!(mmix.pc, 101);
+        assert_eq!(
+            mmix.read_memory(100, 1).unwrap(),
+            &[Instruction::ENTA(42).to_word(DEFAULT_BYTE_SIZE).unwrap()]
+        );
+        mmix.asm_line("STA 200 # store it").unwrap();
+        assert_eq!(mmix.read_memory(200, 1).unwrap(), words(&[42]));
+        assert!(mmix.asm_line("# nothing").is_none());
+        let summary = mmix.asm_line("HLT").unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.pc, 102);
     }
 
     #[test]
-    fn test_parse_value_invalid() {
-        let mut program = Program::new("abc\n");
-        assert_eq!(program.parse_value(), None);
+    fn test_asm_line_statements() {
+        let mut mmix = MMix::new();
+        let summary = mmix.asm_line("ENTA 1; ENTX 2").unwrap();
+        assert_eq!(summary.instructions, 1);
+        assert_eq!(mmix.register(RegisterName::A), 1);
+        assert_eq!(mmix.register(RegisterName::X), 2);
+        assert_eq!(mmix.pc, 2);
+        // a halt stops the rest of the line
+        let summary = mmix.asm_line("HLT; ENTA 3").unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 1);
     }
 
     #[test]
-    fn test_parse_value_empty() {
-        let mut program = Program::new("\n");
-        assert_eq!(program.parse_value(), None);
+    fn test_asm_line_self_jump() {
+        let mut mmix = MMix::new();
+        let summary = mmix.asm_line("JMP 0").unwrap();
+        assert_eq!(summary.instructions, 1);
+        assert_eq!(summary.stop_reason, StopReason::EndOfProgram);
+        assert_eq!(mmix.pc, 0);
     }
 
     #[test]
-    fn test_parse_address() {
-        let mut program = Program::new("128\n");
-        assert_eq!(program.parse_address(), Some(128));
+    fn test_asm_line_strict() {
+        let mut mmix = extended_mmix();
+        mmix.asm_line("ENT8 3").unwrap();
+        assert_eq!(mmix.i[8], 3);
+        let mut mmix = MMix::new();
+        let summary = mmix.asm_line("ENT1 3").unwrap();
+        assert_eq!(summary.stop_reason, StopReason::EndOfProgram);
     }
 
     #[test]
-    fn test_parse_program_load() {
-        let mut program = Program::new("LDA 100\nLDX 200\nLD1 400\nLD5 500\n");
-        program.parse();
+    fn test_peek_instruction() {
+        let mut mmix = MMix::new();
+        mmix.write_memory(
+            10,
+            &[
+                Instruction::LDA(20, Field::WORD)
+                    .to_word(DEFAULT_BYTE_SIZE)
+                    .unwrap(),
+                Word::from(-63),
+            ],
+        )
+        .unwrap();
+        mmix.pc = 3;
         assert_eq!(
-            program.instructions,
-            vec![
-                Instruction::LDA(100),
-                Instruction::LDX(200),
-                Instruction::LDI(1, 400),
-                Instruction::LDI(5, 500),
-            ]
+            mmix.peek_instruction(10),
+            Some(Instruction::LDA(20, Field::WORD))
         );
+        assert_eq!(mmix.peek_instruction(11), None);
+        assert_eq!(mmix.peek_word(11), Some(Word::from(-63)));
+        assert_eq!(mmix.peek_word(DEFAULT_MEMORY_SIZE as u64), None);
+        assert_eq!(mmix.pc, 3);
     }
 
     #[test]
-    fn test_parse_program_load_neg() {
-        let mut program = Program::new("LDAN 100\nLDXN 200\nLD1N 400\nLD5N 500\n");
+    fn test_detect_self_clobber() {
+        let mut program = Program::new("ENTA 0; STA 102; NOP; STA 50; HLT");
         program.parse();
+        let config = MachineConfig::new().with_detect_self_clobber(true);
+        let mut mmix = MMix::with_config(config.clone());
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
         assert_eq!(
-            program.instructions,
-            vec![
-                Instruction::LDAN(100),
-                Instruction::LDXN(200),
-                Instruction::LDIN(1, 400),
-                Instruction::LDIN(5, 500),
-            ]
+            mmix.clobber_events(),
+            &[ClobberEvent { pc: 101, addr: 102 }]
         );
-    }
+        assert_eq!(
+            mmix.clobber_events()[0].to_string(),
+            "store to 102 clobbers code at pc=101"
+        );
+        mmix.clear_clobber_events();
+        assert!(mmix.clobber_events().is_empty());
 
-    #[test]
-    fn test_parse_program_store() {
-        let mut program = Program::new("STA 100\nSTX 200\nSTJ 300\nST1 400\nST5 500\n");
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        mmix.execute_loaded();
+        assert!(mmix.clobber_events().is_empty());
+
+        let mut program = Program::new("ENT1 102; MOVE 50(1); NOP; HLT");
         program.parse();
+        let mut mmix = MMix::with_config(config);
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        mmix.execute_loaded();
         assert_eq!(
-            program.instructions,
-            vec![
-                Instruction::STA(100),
-                Instruction::STX(200),
-                Instruction::STJ(300),
-                Instruction::STI(1, 400),
-                Instruction::STI(5, 500),
-            ]
+            mmix.clobber_events(),
+            &[ClobberEvent { pc: 101, addr: 102 }]
         );
     }
 
     #[test]
-    fn test_parse_program_store_zero() {
-        let mut program = Program::new("STZ 100\n");
+    fn test_state_digest() {
+        let mut program = Program::new("ENTA 5; STA 100; ENTA 0; STA 100");
         program.parse();
-        assert_eq!(program.instructions, vec![Instruction::STZ(100)]);
+        let mut mmix = MMix::new();
+        let start = mmix.state_digest();
+        let mut run = mmix.clone_state();
+        run.execute(&program);
+        assert_ne!(run.state_digest(), start);
+        run.pc = 0;
+        assert_eq!(run.state_digest(), start);
+        mmix.write_memory(7, &words(&[1])).unwrap();
+        assert_ne!(mmix.state_digest(), start);
+        mmix.fill(7, 1, Word::from(0)).unwrap();
+        assert_eq!(mmix.state_digest(), start);
     }
 
     #[test]
-    fn test_parse_program_enter() {
-        let mut program =
-            Program::new("ENTA 100\nENTX 200\nENT1 300\nENNA 300\nENN1 400\nENN5 500\n");
-        program.parse();
-        assert_eq!(
-            program.instructions,
-            vec![
-                Instruction::ENTA(100),
-                Instruction::ENTX(200),
-                Instruction::ENTI(1, 300),
-                Instruction::ENNA(300),
-                Instruction::ENNI(1, 400),
-                Instruction::ENNI(5, 500),
-            ]
-        );
+    fn test_write_read_text() {
+        let mut mmix = MMix::new();
+        assert_eq!(mmix.write_text(100, "SORT ME").unwrap(), 2);
+        assert_eq!(mmix.read_text(100, 2).unwrap(), "SORT ME");
+        assert_eq!(mmix.read_text(100, 1).unwrap(), "SORT");
     }
 
     #[test]
-    fn test_parse_program_add() {
-        let mut program = Program::new("ADD 100\n");
+    fn test_cycles() {
+        let mut program = Program::new("ENTA 5; STA 100; LDX 100; NOP; HLT");
         program.parse();
-        assert_eq!(program.instructions, vec![Instruction::ADD(100)]);
+        let mut mmix = MMix::new();
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.instructions, 5);
+        assert_eq!(summary.cycles, 1 + 2 + 2 + 1 + 10);
+        assert_eq!(summary.mems, 2);
+        assert_eq!(mmix.cycles(), 16);
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.cycles, 16);
+        assert_eq!(mmix.cycles(), 32);
+        assert_eq!(mmix.mems(), 4);
+        assert_eq!(mmix.opcode_stats().count(OpcodeFamily::Store), 2);
+        assert_eq!(mmix.opcode_stats().total(), 10);
     }
 
     #[test]
-    fn test_parse_program_sub() {
-        let mut program = Program::new("SUB 100\n");
+    fn test_program_from_lines() {
+        let mut program = Program::from_lines([(12, "ENTA 5"), (13, "STA 100")]);
         program.parse();
-        assert_eq!(program.instructions, vec![Instruction::SUB(100)]);
+        assert_eq!(
+            program.instructions(),
+            &[Instruction::ENTA(5), Instruction::STA(100, Field::WORD)]
+        );
     }
 
     #[test]
-    fn test_program_ent_sto_a() {
-        let mut program = Program::new("ENTA 112\nSTA 200\n");
+    #[should_panic(expected = "Unknown instruction at line 41")]
+    fn test_program_from_lines_error() {
+        let mut program = Program::from_lines([(40, "ENTA 5"), (41, "FOO 1")]);
         program.parse();
-        let mut mmix = MMix::new();
-        mmix.execute(&program);
-        assert_eq!(mmix.a, 112);
-        assert_eq!(mmix.memory[200], 112);
+    }
+
+    struct StoreEnergy;
+
+    impl EnergyModel for StoreEnergy {
+        fn instruction(&mut self, instruction: &Instruction) -> u64 {
+            instruction.time()
+        }
+
+        fn memory_access(&mut self, addr: u64) -> u64 {
+            if addr >= 1000 {
+                5
+            } else {
+                1
+            }
+        }
     }
 
     #[test]
-    fn test_program_ent_sto_x() {
-        let mut program = Program::new("ENTX 112\nSTX 200\n");
+    fn test_energy_model() {
+        let mut program = Program::new("ENTA 5; STA 100; STA 1000; HLT");
         program.parse();
         let mut mmix = MMix::new();
-        mmix.execute(&program);
-        assert_eq!(mmix.x, 112);
-        assert_eq!(mmix.memory[200], 112);
-    }
+        let mut config = RunConfig::new().energy_model(StoreEnergy);
+        let summary = mmix.execute_with(&program, &mut config);
+        assert_eq!(summary.energy, 1 + (2 + 1) + (2 + 5) + 10);
+        assert_eq!(mmix.execute(&program).energy, 0);
 
-    #[test]
-    fn test_program_ent_sto_i() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("ENT{} 112\nST{} 200\n", i, i).as_str());
-            program.parse();
-            let mut mmix = MMix::new();
-            mmix.execute(&program);
-            assert_eq!(mmix.i[i as usize], 112);
-            assert_eq!(mmix.memory[200], 112);
-        }
+        // MOVE is charged for every word it reads and writes
+        let mut program = Program::new("ENT1 1000; MOVE 100(2); HLT");
+        program.parse();
+        let mut config = RunConfig::new().energy_model(StoreEnergy);
+        let summary = mmix.execute_with(&program, &mut config);
+        assert_eq!(summary.energy, 1 + (5 + 1 + 1 + 5 + 5) + 10);
     }
 
     #[test]
-    fn test_program_ent_sto_neg_a() {
-        let mut program = Program::new("ENNA 112\nSTA 200\n");
+    fn test_program_end() {
+        let mut program = Program::new("START ENTA 5; HLT\nMAIN ENTA 7; HLT\nEND MAIN\nNOP");
         program.parse();
+        // nothing after END is assembled
+        assert_eq!(program.instructions().len(), 4);
+        assert_eq!(program.entry_point(), Some(2));
         let mut mmix = MMix::new();
         mmix.execute(&program);
-        assert_eq!(mmix.a, -112);
-        assert_eq!(mmix.memory[200], -112);
+        assert_eq!(mmix.register(RegisterName::A), 7);
+
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        assert_eq!(mmix.entry_point(), Some(102));
+        mmix.execute_loaded();
+        assert_eq!(mmix.register(RegisterName::A), 7);
+
+        let mut numbered = Program::new(&program.to_canonical_string());
+        numbered.parse();
+        assert_eq!(numbered.entry_point(), Some(2));
     }
 
     #[test]
-    fn test_program_ent_sto_neg_x() {
-        let mut program = Program::new("ENNX 112\nSTX 200\n");
+    fn test_program_end_label() {
+        let mut program = Program::new("JMP END; ENTA 5\nEND HLT");
         program.parse();
+        assert_eq!(program.entry_point(), None);
+        assert_eq!(program.labels()["END"], 2);
         let mut mmix = MMix::new();
-        mmix.execute(&program);
-        assert_eq!(mmix.x, -112);
-        assert_eq!(mmix.memory[200], -112);
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.register(RegisterName::A), 0);
     }
 
     #[test]
-    fn test_program_ent_sto_neg_i() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("ENN{} 112\nST{} 200\n", i, i).as_str());
-            program.parse();
-            let mut mmix = MMix::new();
-            mmix.execute(&program);
-            assert_eq!(mmix.i[i as usize], -112);
-            assert_eq!(mmix.memory[200], -112);
-        }
+    #[should_panic(expected = "Undefined label MAIN at line 1")]
+    fn test_program_end_undefined() {
+        let mut program = Program::new("HLT\nEND MAIN");
+        program.parse();
     }
 
     #[test]
-    fn test_program_load_a() {
-        let mut program = Program::new("LDA 100\n");
+    fn test_program_assert() {
+        let source =
+            "ENTA 5\nASSERT A,>,0,\"rA is positive\"\nENTA 0\nASSERT A,!=,0,\"rA; #not zero\"\nHLT";
+        let mut program = Program::new(source);
         program.parse();
+        let failed = Assertion {
+            message: "rA; #not zero".to_string(),
+            line: 3,
+        };
+        assert_eq!(program.instructions().len(), 9);
+        assert_eq!(program.assertions()[&7], failed);
+
         let mut mmix = MMix::new();
-        mmix.memory[100] = 175;
-        mmix.execute(&program);
-        assert_eq!(mmix.a, 175);
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::AssertionFailed);
+        assert_eq!(summary.pc, 7);
+        assert_eq!(summary.assertion, Some(failed.clone()));
+
+        let mut mmix = MMix::new();
+        mmix.load_program_into_memory(&program, 100).unwrap();
+        let summary = mmix.execute_loaded();
+        assert_eq!(summary.stop_reason, StopReason::AssertionFailed);
+        assert_eq!(summary.assertion, Some(failed));
+        assert!(summary.to_string().contains(
+            "assertion failed at pc=107 [+ 00 00 00 02 05] HLT (\"rA; #not zero\" at line 3);"
+        ));
+
+        // a HLT loaded over the assertion's is a plain halt
+        mmix.load_code(107, &[Instruction::HLT.to_word(64).unwrap()])
+            .unwrap();
+        assert_eq!(mmix.execute_loaded().stop_reason, StopReason::Halted);
     }
 
     #[test]
-    fn test_program_load_x() {
-        let mut program = Program::new("LDX 100\n");
+    fn test_program_assert_holds() {
+        let mut program = Program::new("ENTA 5; ENT3 -1; ASSERT A,>,0; ASSERT 3,<=,0; ENTA 7; HLT");
         program.parse();
+        assert_eq!(program.assertions()[&4].message, "A,>,0");
         let mut mmix = MMix::new();
-        mmix.memory[100] = 175;
-        mmix.execute(&program);
-        assert_eq!(mmix.x, 175);
-    }
+        let summary = mmix.execute(&program);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.assertion, None);
+        assert_eq!(mmix.register(RegisterName::A), 7);
+        // JSJ steps over the HLT without setting rJ
+        assert_eq!(mmix.register(RegisterName::J), 0);
 
-    #[test]
-    fn test_program_load_i() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("LD{} 100\n", i).as_str());
-            program.parse();
-            let mut mmix = MMix::new();
-            mmix.memory[100] = 175;
-            mmix.execute(&program);
-            assert_eq!(mmix.i[i as usize], 175);
-        }
+        let mut program = Program::new("NOP; ASSERT X,=,0");
+        program.parse_with(true);
+        assert_eq!(program.assertions().keys().collect::<Vec<_>>(), [&2]);
     }
 
     #[test]
-    fn test_program_load_neg_a() {
-        let mut program = Program::new("LDAN 100\n");
+    #[should_panic(expected = "Invalid assertion at line 1")]
+    fn test_program_assert_nonzero() {
+        let mut program = Program::new("HLT\nASSERT A,>,1,\"one\"");
         program.parse();
-        let mut mmix = MMix::new();
-        mmix.memory[100] = -175;
-        mmix.execute(&program);
-        assert_eq!(mmix.a, 175);
     }
 
     #[test]
-    fn test_program_load_neg_x() {
-        let mut program = Program::new("LDXN 100\n");
-        program.parse();
-        let mut mmix = MMix::new();
-        mmix.memory[100] = -175;
-        mmix.execute(&program);
-        assert_eq!(mmix.x, 175);
+    fn test_program_canonical_string() {
+        let mut a = Program::new("  ENTA 5 ;STA 100 # save\nENN3 2\n\nHLT");
+        a.parse();
+        let mut b = Program::new("ENTA 5\nSTA 100\nENN3 2\nHLT\n");
+        b.parse();
+        assert_eq!(a.to_canonical_string(), "ENTA 5\nSTA 100\nENN3 2\nHLT\n");
+        assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+        let mut c = Program::new(&a.to_canonical_string());
+        c.parse();
+        assert_eq!(c.instructions(), a.instructions());
     }
 
+    fn assert_send_sync<T: Send + Sync>() {}
+
     #[test]
-    fn test_program_load_neg_i() {
-        for i in 1..10 {
-            let mut program = Program::new(format!("LD{}N 100\n", i).as_str());
-            program.parse();
-            let mut mmix = MMix::new();
-            mmix.memory[100] = -175;
-            mmix.execute(&program);
-            assert_eq!(mmix.i[i as usize], 175);
+    fn test_thread_safety() {
+        assert_send_sync::<MMix>();
+        assert_send_sync::<MachineConfig>();
+        assert_send_sync::<RunSummary>();
+        assert_send_sync::<Instruction>();
+        assert_send_sync::<CardDeck>();
+
+        let mut program = Program::new("ENTA 5; STA 100");
+        program.parse();
+        let mmix = MMix::new();
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let mut fork = mmix.clone_state();
+                let words = program.assemble();
+                std::thread::spawn(move || {
+                    fork.load_code(10, &words).unwrap();
+                    fork.execute_from_memory(10);
+                    fork.read_memory(100, 1).unwrap()[0]
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Word::from(5));
         }
     }
 
     #[test]
-    fn test_program_add() {
-        let mut program = Program::new("ADD 100\n");
+    fn test_tracer() {
+        let mut program = Program::new("ENTA 5; STA 100; STA 100; ENT2 -1; HLT");
         program.parse();
         let mut mmix = MMix::new();
-        mmix.a = 100;
-        mmix.memory[100] = 75;
-        mmix.execute(&program);
-        assert_eq!(mmix.a, 175);
+        let mut events = Vec::new();
+        mmix.execute_with(&program, &mut RunConfig::new().tracer(&mut events));
+        let lines: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0000: ENTA 5",
+                "      rA 0 -> 5",
+                "0001: STA 100",
+                "      M[100] + 00 00 00 00 00 -> + 00 00 00 00 05",
+                "0002: STA 100",
+                "0003: ENT2 -1",
+                "      rI2 0 -> -1",
+                "0004: HLT",
+            ]
+        );
     }
 
     #[test]
-    fn test_program_sub() {
-        let mut program = Program::new("SUB 100\n");
+    fn test_tracer_move() {
+        let mut program = Program::new("ENTA 5; STA 100; ENT1 200; MOVE 99(2)");
         program.parse();
         let mut mmix = MMix::new();
-        mmix.a = 100;
-        mmix.memory[100] = 75;
-        mmix.execute(&program);
-        assert_eq!(mmix.a, 25);
+        let mut events = Vec::new();
+        mmix.execute_with(&program, &mut RunConfig::new().tracer(&mut events));
+        let lines: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+        assert_eq!(
+            &lines[6..],
+            [
+                "0003: MOVE 99(2)",
+                "      rI1 200 -> 202",
+                "      M[201] + 00 00 00 00 00 -> + 00 00 00 00 05",
+            ]
+        );
     }
 
     #[test]
-    fn test_program_add_overflow() {
-        let mut program = Program::new("ADD 100\n");
+    fn test_tracer_effective_address() {
+        let mut program = Program::new("ENT1 2; LDA 100,1; ENTA 3,1; JMP 2,1");
         program.parse();
         let mut mmix = MMix::new();
-        mmix.a = 100;
-        mmix.memory[100] = i64::MAX;
-        mmix.execute(&program);
-        assert_eq!(mmix.overflow, true);
+        let mut events = Vec::new();
+        mmix.execute_with(&program, &mut RunConfig::new().tracer(&mut events));
+        let executed: Vec<(Instruction, Option<u64>)> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                TraceEvent::Execute {
+                    effective, address, ..
+                } => Some((effective, address)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            executed,
+            vec![
+                (Instruction::ENTI(reg(1), 2), None),
+                (Instruction::LDA(102, Field::WORD), Some(102)),
+                (Instruction::ENTA(5), None),
+                (Instruction::JMP(4), Some(4)),
+            ]
+        );
     }
 
     #[test]
-    fn test_program_sub_overflow() {
-        let mut program = Program::new("SUB 100\n");
+    fn test_trace_log() {
+        let mut program = Program::new("ENTA 5; STA 100; STA 100; ENT2 -1; MOVE 100(1); HLT");
         program.parse();
         let mut mmix = MMix::new();
-        mmix.a = 100;
-        mmix.memory[100] = i64::MIN;
-        mmix.execute(&program);
-        assert_eq!(mmix.a, i64::MIN + 100);
-        assert_eq!(mmix.overflow, true);
+        let mut log = TraceLog::new();
+        mmix.execute_with(&program, &mut RunConfig::new().tracer(&mut log));
+        assert_eq!(
+            log.to_string(),
+            "\
+0000 [+ 00 05 00 02 48] ENTA 5; rA=5
+0001 [+ 01 36 00 05 24] STA 100; M[100]=[+ 00 00 00 00 05]
+0002 [+ 01 36 00 05 24] STA 100
+0003 [- 00 01 00 02 50] ENT2 -1; rI2=-1
+0004 [+ 01 36 00 01 07] MOVE 100(1); rI1=1 M[0]=[+ 00 00 00 00 05]
+0005 [+ 00 00 00 02 05] HLT
+"
+        );
     }
 }
@@ -1,3 +1,83 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use checksmix::{
+    CardDeck, InstructionSet, LinePrinter, MMix, MachineConfig, PaperTape, Program, RunConfig,
+    TraceEvent, Tracer, LINE_PRINTER, PAPER_TAPE,
+};
+
+struct PrintTracer;
+
+impl Tracer for PrintTracer {
+    fn event(&mut self, event: &TraceEvent) {
+        println!("{}", event);
+    }
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let summary =
+        args.iter().any(|arg| arg == "--summary") || env::var("CHECKSMIX_SUMMARY").is_ok();
+    let stats = args.iter().any(|arg| arg == "--stats");
+    let trace = args.iter().any(|arg| arg == "--trace");
+    let instruction_set = if args.iter().any(|arg| arg == "--extended") {
+        InstructionSet::Extended
+    } else {
+        InstructionSet::Strict
+    };
+    let path = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => path,
+        None => {
+            eprintln!(
+                "usage: checksmix [--summary] [--stats] [--trace] [--extended] [--cards=FILE] [--paper-tape=FILE] <program.mix>"
+            );
+            process::exit(2);
+        }
+    };
+    let source = read(path);
+    let mut program = Program::with_instruction_set(&source, instruction_set);
+    program.parse();
+    let mut mmix = MMix::with_config(MachineConfig::new().with_instruction_set(instruction_set));
+    if let Some(cards) = option(&args, "--cards=") {
+        mmix.load_deck(&CardDeck::parse(&read(cards)));
+    }
+    if let Some(tape) = option(&args, "--paper-tape=") {
+        mmix.attach(PAPER_TAPE, PaperTape::parse(&read(tape)));
+    }
+    let mut config = RunConfig::new();
+    if trace {
+        config = config.tracer(PrintTracer);
+    }
+    let result = mmix.execute_with(&program, &mut config);
+    if let Some(printer) = mmix.device::<LinePrinter>(LINE_PRINTER) {
+        print!("{}", printer.text());
+    }
+    if let Some(typewriter) = mmix.device::<PaperTape>(PAPER_TAPE) {
+        print!("{}", typewriter.typed());
+    }
+    if summary {
+        println!("{}", result);
+    }
+    if stats {
+        print!("{}", mmix.opcode_stats());
+    }
+    if let Some(assertion) = &result.assertion {
+        eprintln!("assertion failed: {}", assertion);
+        process::exit(1);
+    }
+}
+
+fn option<'a>(args: &'a [String], prefix: &str) -> Option<&'a str> {
+    args.iter().find_map(|arg| arg.strip_prefix(prefix))
+}
+
+fn read(path: &str) -> String {
+    match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::ops::Index;
+use std::sync::Arc;
+
+use crate::Word;
+
+/// Why the host could not use an address of a machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// `len` words from `addr` do not fit in memory.
+    OutsideMemory { addr: u64, len: usize },
+    /// The address holds no loaded code, and the machine checks for it.
+    OutsideLoadedCode(u64),
+    /// There is no input-output unit with this number.
+    InvalidUnit(u8),
+}
+
+/// Words in each page of `Memory`.
+const PAGE_SIZE: usize = 64;
+
+/// Word memory shared between cloned machines page by page. A write copies
+/// only the page it lands on, so a fork pays for the pages it touches and
+/// keeps sharing the rest.
+///
+/// A digest of the contents is kept up to date by `set` and `write`.
+#[derive(Debug, Clone)]
+pub(crate) struct Memory {
+    pages: Vec<Arc<[Word; PAGE_SIZE]>>,
+    len: usize,
+    digest: u64,
+}
+
+/// splitmix64 finalizer, used to spread values before summing them.
+pub(crate) fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn word_hash(addr: usize, word: Word) -> u64 {
+    mix(mix(addr as u64) ^ word.bits())
+}
+
+fn hash_words(words: impl IntoIterator<Item = Word>) -> u64 {
+    words.into_iter().enumerate().fold(0, |sum, (addr, word)| {
+        sum.wrapping_add(word_hash(addr, word))
+    })
+}
+
+impl Memory {
+    pub(crate) fn new(size: usize) -> Self {
+        // every page starts as the same page of zeros
+        let page = Arc::new([Word::default(); PAGE_SIZE]);
+        Self {
+            pages: vec![page; size.div_ceil(PAGE_SIZE)],
+            len: size,
+            digest: hash_words(std::iter::repeat_n(Word::default(), size)),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether every page is still shared with `other`.
+    pub(crate) fn is_shared_with(&self, other: &Memory) -> bool {
+        self.len == other.len
+            && self
+                .pages
+                .iter()
+                .zip(&other.pages)
+                .all(|(page, other)| Arc::ptr_eq(page, other))
+    }
+
+    pub(crate) fn get(&self, addr: usize) -> Option<Word> {
+        (addr < self.len).then(|| self.pages[addr / PAGE_SIZE][addr % PAGE_SIZE])
+    }
+
+    /// The `len` words from `addr`.
+    pub(crate) fn read(&self, addr: usize, len: usize) -> Vec<Word> {
+        (addr..addr + len).map(|addr| self[addr]).collect()
+    }
+
+    pub(crate) fn set(&mut self, addr: usize, value: Word) {
+        assert!(addr < self.len, "Address {} is outside memory", addr);
+        let page = Arc::make_mut(&mut self.pages[addr / PAGE_SIZE]);
+        let old = std::mem::replace(&mut page[addr % PAGE_SIZE], value);
+        self.digest = self
+            .digest
+            .wrapping_sub(word_hash(addr, old))
+            .wrapping_add(word_hash(addr, value));
+    }
+
+    pub(crate) fn write(&mut self, addr: usize, values: &[Word]) {
+        for (offset, &value) in values.iter().enumerate() {
+            self.set(addr + offset, value);
+        }
+    }
+
+    pub(crate) fn digest(&self) -> u64 {
+        self.digest
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = Word;
+
+    fn index(&self, addr: usize) -> &Word {
+        assert!(addr < self.len, "Address {} is outside memory", addr);
+        &self.pages[addr / PAGE_SIZE][addr % PAGE_SIZE]
+    }
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressError::OutsideMemory { addr, len: 1 } => {
+                write!(f, "address {} is outside memory", addr)
+            }
+            AddressError::OutsideMemory { addr, len } => write!(
+                f,
+                "addresses {}..{} are outside memory",
+                addr,
+                *addr as u128 + *len as u128
+            ),
+            AddressError::OutsideLoadedCode(addr) => {
+                write!(f, "address {} is outside loaded code", addr)
+            }
+            AddressError::InvalidUnit(unit) => write!(f, "unit {} does not exist", unit),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_copy_on_write() {
+        let mut memory = Memory::new(10);
+        memory.set(1, Word::from(5));
+        let mut copy = memory.clone();
+        assert!(copy.is_shared_with(&memory));
+        copy.set(1, Word::from(6));
+        assert!(!copy.is_shared_with(&memory));
+        assert_eq!(memory[1], Word::from(5));
+        assert_eq!(copy[1], Word::from(6));
+    }
+
+    #[test]
+    fn test_memory_pages() {
+        let mut memory = Memory::new(4000);
+        assert_eq!(memory.pages.len(), 63);
+        memory.write(100, &[Word::from(1); 100]);
+        let mut copy = memory.clone();
+        copy.set(130, Word::from(2));
+        // only the page holding 130 was copied
+        let shared = |a: &Memory, b: &Memory| {
+            a.pages
+                .iter()
+                .zip(&b.pages)
+                .filter(|(a, b)| Arc::ptr_eq(a, b))
+                .count()
+        };
+        assert_eq!(shared(&memory, &copy), 62);
+        assert!(!Arc::ptr_eq(&copy.pages[2], &memory.pages[2]));
+        assert_eq!(
+            copy.read(129, 3),
+            vec![Word::from(1), Word::from(2), Word::from(1)]
+        );
+        assert_eq!(memory[130], Word::from(1));
+        assert_eq!(memory.get(3999), Some(Word::default()));
+        assert_eq!(memory.get(4000), None);
+    }
+
+    #[test]
+    fn test_address_error_display() {
+        let error = AddressError::OutsideMemory { addr: 4000, len: 1 };
+        assert_eq!(error.to_string(), "address 4000 is outside memory");
+        let error = AddressError::OutsideMemory {
+            addr: 3990,
+            len: 16,
+        };
+        assert_eq!(error.to_string(), "addresses 3990..4006 are outside memory");
+        let error = AddressError::OutsideLoadedCode(7);
+        assert_eq!(error.to_string(), "address 7 is outside loaded code");
+    }
+
+    #[test]
+    fn test_memory_digest() {
+        let mut memory = Memory::new(10);
+        let empty = memory.digest();
+        memory.write(2, &[Word::from(7), Word::from(8)]);
+        assert_eq!(memory.digest(), hash_words(memory.read(0, memory.len())));
+        assert_ne!(memory.digest(), empty);
+        memory.write(2, &[Word::default(), Word::default().negated()]);
+        assert_ne!(memory.digest(), empty);
+        memory.set(3, Word::default());
+        assert_eq!(memory.digest(), empty);
+        memory.set(4, Word::from(1));
+        assert_eq!(memory.digest(), hash_words(memory.read(0, memory.len())));
+    }
+}
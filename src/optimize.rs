@@ -0,0 +1,409 @@
+use std::collections::BTreeSet;
+
+use crate::{Field, Instruction};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Register {
+    A,
+    X,
+    I(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizeReport {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+impl OptimizeReport {
+    pub fn saved(&self) -> usize {
+        self.before.len() - self.after.len()
+    }
+}
+
+// the register an instruction overwrites without reading it first
+fn overwritten(instruction: &Instruction) -> Option<Register> {
+    match instruction {
+        Instruction::LDA(..)
+        | Instruction::LDAN(..)
+        | Instruction::ENTA(_)
+        | Instruction::ENNA(_) => Some(Register::A),
+        Instruction::LDX(..)
+        | Instruction::LDXN(..)
+        | Instruction::ENTX(_)
+        | Instruction::ENNX(_) => Some(Register::X),
+        Instruction::LDI(n, ..)
+        | Instruction::LDIN(n, ..)
+        | Instruction::ENTI(n, _)
+        | Instruction::ENNI(n, _) => Some(Register::I(n.number())),
+        _ => None,
+    }
+}
+
+// the register set to +0, which STZ stores. ENNA 0 and ENNX 0 set -0, but
+// index registers have no -0.
+fn entered_zero(instruction: &Instruction) -> Option<Register> {
+    match instruction {
+        Instruction::ENTA(0) => Some(Register::A),
+        Instruction::ENTX(0) => Some(Register::X),
+        Instruction::ENTI(n, 0) | Instruction::ENNI(n, 0) => Some(Register::I(n.number())),
+        _ => None,
+    }
+}
+
+// the register an INC or DEC changes and by how much
+fn increment(instruction: &Instruction) -> Option<(Register, i64)> {
+    match instruction {
+        Instruction::INCA(m) => Some((Register::A, *m)),
+        Instruction::INCX(m) => Some((Register::X, *m)),
+        Instruction::INCI(n, m) => Some((Register::I(n.number()), *m)),
+        Instruction::DECA(m) => Some((Register::A, -m)),
+        Instruction::DECX(m) => Some((Register::X, -m)),
+        Instruction::DECI(n, m) => Some((Register::I(n.number()), -m)),
+        _ => None,
+    }
+}
+
+// the INC or DEC that also adds `delta`, if it can be encoded
+fn add_increment(instruction: &Instruction, delta: i64) -> Option<Instruction> {
+    let sum = match instruction {
+        Instruction::INCA(m) => Instruction::INCA(m + delta),
+        Instruction::INCX(m) => Instruction::INCX(m + delta),
+        Instruction::INCI(n, m) => Instruction::INCI(*n, m + delta),
+        Instruction::DECA(m) => Instruction::DECA(m - delta),
+        Instruction::DECX(m) => Instruction::DECX(m - delta),
+        Instruction::DECI(n, m) => Instruction::DECI(*n, m - delta),
+        _ => return None,
+    };
+    sum.encode().map(|_| sum)
+}
+
+fn stored(instruction: &Instruction) -> Option<(Register, u64, Field)> {
+    match instruction {
+        Instruction::STA(addr, field) => Some((Register::A, *addr, *field)),
+        Instruction::STX(addr, field) => Some((Register::X, *addr, *field)),
+        Instruction::STI(n, addr, field) => Some((Register::I(n.number()), *addr, *field)),
+        _ => None,
+    }
+}
+
+// the address part M of an instruction
+fn address(instruction: &Instruction) -> Option<i64> {
+    instruction
+        .encode()
+        .map(|word| Field::ADDRESS.extract(word))
+}
+
+// the instruction numbers that jumps, labels and anchors refer to, or
+// None if an indexed jump's target is only known at run time, so no
+// instruction may move
+fn targets(
+    instructions: &[Instruction],
+    relocatable: &BTreeSet<usize>,
+    anchors: &[u64],
+) -> Option<Vec<u64>> {
+    let mut targets = anchors.to_vec();
+    for (pc, instruction) in instructions.iter().enumerate() {
+        if let Instruction::Indexed(_, jump) = instruction {
+            if jump.jump_address().is_some() {
+                return None;
+            }
+        }
+        targets.extend(instruction.jump_address());
+        if relocatable.contains(&pc) {
+            targets.extend(address(instruction).and_then(|addr| u64::try_from(addr).ok()));
+        }
+    }
+    Some(targets)
+}
+
+// remove the instruction at `pc`, moving later jump targets, labels and
+// anchors back
+fn remove(
+    instructions: &mut Vec<Instruction>,
+    relocatable: &mut BTreeSet<usize>,
+    anchors: &mut [u64],
+    pc: usize,
+) {
+    instructions.remove(pc);
+    for anchor in anchors.iter_mut().filter(|anchor| **anchor > pc as u64) {
+        *anchor -= 1;
+    }
+    *relocatable = relocatable
+        .iter()
+        .filter(|&&n| n != pc)
+        .map(|&n| if n > pc { n - 1 } else { n })
+        .collect();
+    for (n, instruction) in instructions.iter_mut().enumerate() {
+        let target = if relocatable.contains(&n) {
+            address(instruction)
+        } else {
+            instruction.jump_address().map(|addr| addr as i64)
+        };
+        if target.is_some_and(|addr| addr > pc as i64) {
+            *instruction = instruction.offset_address(-1).unwrap();
+        }
+    }
+}
+
+fn listing(instructions: &[Instruction]) -> Vec<String> {
+    instructions.iter().map(|i| i.to_string()).collect()
+}
+
+fn fold_once(
+    instructions: &mut Vec<Instruction>,
+    relocatable: &mut BTreeSet<usize>,
+    anchors: &mut [u64],
+) -> bool {
+    let Some(targets) = targets(instructions, relocatable, anchors) else {
+        return false;
+    };
+    if let Some(pc) = instructions.iter().position(|i| *i == Instruction::NOP) {
+        remove(instructions, relocatable, anchors, pc);
+        return true;
+    }
+    // INCA 2, INCA 3 is INCA 5 when both go the same way, since then an
+    // overflow of the first is the same overflow in the sum. Labels stay
+    // separate, as does an INC something jumps to.
+    for pc in 1..instructions.len() {
+        let (Some((first, a)), Some((second, b))) = (
+            increment(&instructions[pc - 1]),
+            increment(&instructions[pc]),
+        ) else {
+            continue;
+        };
+        if first != second
+            || a.signum() * b.signum() < 0
+            || targets.contains(&(pc as u64))
+            || relocatable.contains(&(pc - 1))
+            || relocatable.contains(&pc)
+        {
+            continue;
+        }
+        if let Some(sum) = add_increment(&instructions[pc - 1], b) {
+            instructions[pc - 1] = sum;
+            remove(instructions, relocatable, anchors, pc);
+            return true;
+        }
+    }
+    for pc in 0..instructions.len() {
+        let reg = overwritten(&instructions[pc]);
+        if reg.is_none() {
+            continue;
+        }
+        // a register load immediately overwritten by another is dead
+        if pc + 1 < instructions.len() && overwritten(&instructions[pc + 1]) == reg {
+            remove(instructions, relocatable, anchors, pc);
+            return true;
+        }
+        // ENTA 0, STA m before A is overwritten is just STZ m, unless
+        // something jumps straight to the STA
+        if pc + 2 < instructions.len()
+            && entered_zero(&instructions[pc]) == reg
+            && !targets.contains(&(pc as u64 + 1))
+        {
+            if let Some((stored_reg, addr, field)) = stored(&instructions[pc + 1]) {
+                if Some(stored_reg) == reg && overwritten(&instructions[pc + 2]) == reg {
+                    instructions[pc + 1] = Instruction::STZ(addr, field);
+                    remove(instructions, relocatable, anchors, pc);
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+// `relocatable` holds the instructions whose address is a label, and
+// `anchors` the instruction numbers kept outside the code, such as the
+// entry point, which move with their instructions
+pub fn optimize(
+    instructions: &mut Vec<Instruction>,
+    relocatable: &mut BTreeSet<usize>,
+    anchors: &mut [u64],
+) -> OptimizeReport {
+    let before = listing(instructions);
+    while fold_once(instructions, relocatable, anchors) {}
+    OptimizeReport {
+        before,
+        after: listing(instructions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IndexReg, RegisterTest};
+
+    fn reg(n: u8) -> IndexReg {
+        IndexReg::try_from(n).unwrap()
+    }
+
+    #[test]
+    fn test_optimize_nop() {
+        let mut instructions = vec![Instruction::NOP, Instruction::ENTA(1), Instruction::NOP];
+        let report = optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        assert_eq!(instructions, vec![Instruction::ENTA(1)]);
+        assert_eq!(report.saved(), 2);
+    }
+
+    #[test]
+    fn test_optimize_anchors() {
+        let mut instructions = vec![Instruction::NOP, Instruction::HLT, Instruction::ENTA(1)];
+        let mut anchors = [2, 0];
+        optimize(&mut instructions, &mut BTreeSet::new(), &mut anchors);
+        assert_eq!(instructions, vec![Instruction::HLT, Instruction::ENTA(1)]);
+        assert_eq!(anchors, [1, 0]);
+    }
+
+    #[test]
+    fn test_optimize_dead_load() {
+        let mut instructions = vec![
+            Instruction::ENTA(1),
+            Instruction::LDA(100, Field::WORD),
+            Instruction::ENTI(reg(1), 5),
+            Instruction::LDI(reg(2), 100, Field::WORD),
+            Instruction::LDI(reg(1), 100, Field::WORD),
+            Instruction::ADD(100, Field::WORD),
+        ];
+        optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::LDA(100, Field::WORD),
+                Instruction::ENTI(reg(1), 5),
+                Instruction::LDI(reg(2), 100, Field::WORD),
+                Instruction::LDI(reg(1), 100, Field::WORD),
+                Instruction::ADD(100, Field::WORD),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optimize_store_zero() {
+        let mut instructions = vec![
+            Instruction::ENTA(0),
+            Instruction::STA(100, Field::WORD),
+            Instruction::LDA(200, Field::WORD),
+            Instruction::ENTX(0),
+            Instruction::STX(101, Field::WORD),
+            Instruction::HLT,
+        ];
+        let report = optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::STZ(100, Field::WORD),
+                Instruction::LDA(200, Field::WORD),
+                Instruction::ENTX(0),
+                Instruction::STX(101, Field::WORD),
+                Instruction::HLT,
+            ]
+        );
+        assert_eq!(report.saved(), 1);
+        assert_eq!(report.before[0], "ENTA 0");
+        assert_eq!(report.after[0], "STZ 100");
+    }
+
+    #[test]
+    fn test_optimize_store_negative_zero() {
+        // ENNA 0 sets -0, which STZ would store as +0
+        let mut instructions = vec![
+            Instruction::ENNA(0),
+            Instruction::STA(100, Field::WORD),
+            Instruction::LDA(200, Field::WORD),
+        ];
+        let report = optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        assert_eq!(report.saved(), 0);
+    }
+
+    #[test]
+    fn test_optimize_increments() {
+        let mut instructions = vec![
+            Instruction::INCA(2),
+            Instruction::INCA(3),
+            Instruction::DECA(-1),
+            Instruction::DECX(4),
+            Instruction::INCX(1),
+            Instruction::INCI(reg(1), 1),
+            Instruction::INCI(reg(2), 1),
+            Instruction::DECI(reg(2), 1),
+            Instruction::DECI(reg(2), 2),
+            Instruction::JMP(8),
+        ];
+        let report = optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        // opposite directions and jump targets stay apart
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::INCA(6),
+                Instruction::DECX(4),
+                Instruction::INCX(1),
+                Instruction::INCI(reg(1), 1),
+                Instruction::INCI(reg(2), 1),
+                Instruction::DECI(reg(2), 1),
+                Instruction::DECI(reg(2), 2),
+                Instruction::JMP(6),
+            ]
+        );
+        assert_eq!(report.saved(), 2);
+        assert_eq!(report.after[0], "INCA 6");
+    }
+
+    #[test]
+    fn test_optimize_jumps() {
+        let mut instructions = vec![
+            Instruction::NOP,
+            Instruction::ENTA(0),
+            Instruction::STA(100, Field::WORD),
+            Instruction::LDA(200, Field::WORD),
+            Instruction::JMP(2),
+            Instruction::JA(RegisterTest::Negative, 4),
+        ];
+        optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        // the NOP goes and the jumps move back with the code, but the
+        // ENTA 0 stays because something jumps to its STA
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::ENTA(0),
+                Instruction::STA(100, Field::WORD),
+                Instruction::LDA(200, Field::WORD),
+                Instruction::JMP(1),
+                Instruction::JA(RegisterTest::Negative, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optimize_labels() {
+        let mut instructions = vec![
+            Instruction::NOP,
+            Instruction::ENTA(0),
+            Instruction::LDX(3, Field::WORD),
+            Instruction::ENTX(4),
+            Instruction::HLT,
+        ];
+        let mut relocatable = BTreeSet::from([2, 3]);
+        optimize(&mut instructions, &mut relocatable, &mut []);
+        // ENTX 4 moves with the HLT it names, while the dead LDX goes
+        assert_eq!(
+            instructions,
+            vec![Instruction::ENTA(0), Instruction::ENTX(2), Instruction::HLT]
+        );
+        assert_eq!(relocatable, BTreeSet::from([1]));
+    }
+
+    #[test]
+    fn test_optimize_indexed_jump() {
+        let mut instructions = vec![
+            Instruction::NOP,
+            Instruction::Indexed(
+                IndexReg::try_from(1).unwrap(),
+                Box::new(Instruction::JMP(0)),
+            ),
+        ];
+        let report = optimize(&mut instructions, &mut BTreeSet::new(), &mut []);
+        assert_eq!(report.saved(), 0);
+    }
+}
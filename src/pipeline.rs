@@ -0,0 +1,187 @@
+//! The steps from source text to a finished run, as replaceable stages.
+//!
+//! `Pipeline::new()` wires up the standard stages. Any of them can be
+//! swapped out, for example to wrap the loader with instrumentation.
+
+use crate::{AddressError, MMix, MachineConfig, Program, RunSummary, Word};
+
+pub trait Parse {
+    fn parse(&mut self, source: &str, config: &MachineConfig) -> Program;
+}
+
+pub trait Assemble {
+    /// The program's words for loading at `origin`.
+    fn assemble(&mut self, program: &Program, origin: u64) -> Vec<Word>;
+}
+
+pub trait Load {
+    /// Put `words` in memory at `origin`, or say why they do not fit.
+    fn load(&mut self, mmix: &mut MMix, origin: u64, words: &[Word]) -> Result<(), AddressError>;
+}
+
+pub trait Execute {
+    fn execute(&mut self, mmix: &mut MMix, start: u64) -> RunSummary;
+}
+
+/// The stages used by `Pipeline::new()`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Standard;
+
+impl Parse for Standard {
+    fn parse(&mut self, source: &str, config: &MachineConfig) -> Program {
+        let mut program = Program::with_config(source, config);
+        program.parse();
+        program
+    }
+}
+
+impl Assemble for Standard {
+    fn assemble(&mut self, program: &Program, origin: u64) -> Vec<Word> {
+        program.assemble_at(origin)
+    }
+}
+
+impl Load for Standard {
+    fn load(&mut self, mmix: &mut MMix, origin: u64, words: &[Word]) -> Result<(), AddressError> {
+        mmix.load_code(origin, words)
+    }
+}
+
+impl Execute for Standard {
+    fn execute(&mut self, mmix: &mut MMix, start: u64) -> RunSummary {
+        mmix.execute_from_memory(start)
+    }
+}
+
+pub struct Pipeline {
+    parse: Box<dyn Parse>,
+    assemble: Box<dyn Assemble>,
+    load: Box<dyn Load>,
+    execute: Box<dyn Execute>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self {
+            parse: Box::new(Standard),
+            assemble: Box::new(Standard),
+            load: Box::new(Standard),
+            execute: Box::new(Standard),
+        }
+    }
+
+    pub fn parser(mut self, stage: impl Parse + 'static) -> Self {
+        self.parse = Box::new(stage);
+        self
+    }
+
+    pub fn assembler(mut self, stage: impl Assemble + 'static) -> Self {
+        self.assemble = Box::new(stage);
+        self
+    }
+
+    pub fn loader(mut self, stage: impl Load + 'static) -> Self {
+        self.load = Box::new(stage);
+        self
+    }
+
+    pub fn executor(mut self, stage: impl Execute + 'static) -> Self {
+        self.execute = Box::new(stage);
+        self
+    }
+
+    /// Parse `source`, load it at `origin` and run it from there, or from
+    /// the address its `END` names. Fails if the code does not fit in
+    /// memory.
+    pub fn run(
+        &mut self,
+        mmix: &mut MMix,
+        source: &str,
+        origin: u64,
+    ) -> Result<RunSummary, AddressError> {
+        let program = self.parse.parse(source, mmix.config());
+        let words = self.assemble.assemble(&program, origin);
+        self.load.load(mmix, origin, &words)?;
+        let start = origin + program.entry_point().unwrap_or(0);
+        Ok(self.execute.execute(mmix, start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Instruction, StopReason};
+
+    #[test]
+    fn test_standard_pipeline() {
+        let mut mmix = MMix::new();
+        let summary = Pipeline::new()
+            .run(&mut mmix, "ENTA 7; STA 50; HLT", 100)
+            .unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(mmix.read_memory(50, 1).unwrap(), &[Word::from(7)]);
+        assert!(mmix.is_loaded_code(102));
+    }
+
+    #[test]
+    fn test_pipeline_relocates_jumps() {
+        let mut mmix = MMix::new();
+        let summary = Pipeline::new()
+            .run(&mut mmix, "JMP DONE; HLT; DONE ENTA 7; HLT", 100)
+            .unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.pc, 103);
+    }
+
+    #[test]
+    fn test_pipeline_outside_memory() {
+        let mut mmix = MMix::new();
+        assert_eq!(
+            Pipeline::new().run(&mut mmix, "ENTA 1; HLT", 3999),
+            Err(AddressError::OutsideMemory { addr: 3999, len: 2 })
+        );
+        assert!(!mmix.is_loaded_code(3999));
+    }
+
+    #[test]
+    fn test_pipeline_end() {
+        let mut mmix = MMix::new();
+        let source = "HLT; MAIN ENTA 7; HLT; END MAIN";
+        let summary = Pipeline::new().run(&mut mmix, source, 100).unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.pc, 102);
+    }
+
+    // appends a HLT so snippets without one stop at the end of the code
+    struct HaltingLoader;
+
+    impl Load for HaltingLoader {
+        fn load(
+            &mut self,
+            mmix: &mut MMix,
+            origin: u64,
+            words: &[Word],
+        ) -> Result<(), AddressError> {
+            let mut words = words.to_vec();
+            words.push(Instruction::HLT.to_word(mmix.config().byte_size()).unwrap());
+            mmix.load_code(origin, &words)
+        }
+    }
+
+    #[test]
+    fn test_custom_loader() {
+        let mut mmix = MMix::new();
+        let summary = Pipeline::new()
+            .loader(HaltingLoader)
+            .run(&mut mmix, "ENTA 7", 100)
+            .unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.pc, 101);
+    }
+}
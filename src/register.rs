@@ -0,0 +1,165 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+pub const MAX_INDEX_REGISTER: u8 = 9;
+pub const STRICT_INDEX_REGISTERS: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IndexReg(u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidIndexReg(pub u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterName {
+    A,
+    X,
+    I(IndexReg),
+    J,
+}
+
+/// The registers and indicators of a machine at one moment, as reported
+/// in a `RunSummary`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registers {
+    pub a: i64,
+    pub x: i64,
+    /// rI1 onwards, as many as the instruction set has.
+    pub i: Vec<i64>,
+    pub j: u64,
+    pub overflow: bool,
+    /// The comparison indicator, or None before any comparison was made.
+    pub comparison: Option<Ordering>,
+}
+
+impl IndexReg {
+    pub fn number(&self) -> u8 {
+        self.0
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.0 > STRICT_INDEX_REGISTERS
+    }
+}
+
+impl TryFrom<u8> for IndexReg {
+    type Error = InvalidIndexReg;
+
+    fn try_from(n: u8) -> Result<Self, Self::Error> {
+        if (1..=MAX_INDEX_REGISTER).contains(&n) {
+            Ok(Self(n))
+        } else {
+            Err(InvalidIndexReg(n))
+        }
+    }
+}
+
+impl From<IndexReg> for u8 {
+    fn from(reg: IndexReg) -> Self {
+        reg.0
+    }
+}
+
+impl fmt::Display for IndexReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rI{}", self.0)
+    }
+}
+
+impl fmt::Display for RegisterName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegisterName::A => write!(f, "rA"),
+            RegisterName::X => write!(f, "rX"),
+            RegisterName::I(n) => write!(f, "{}", n),
+            RegisterName::J => write!(f, "rJ"),
+        }
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rA={} rX={}", self.a, self.x)?;
+        for (n, value) in self.i.iter().enumerate() {
+            write!(f, " rI{}={}", n + 1, value)?;
+        }
+        let cmp = match self.comparison {
+            Some(Ordering::Less) => "L",
+            Some(Ordering::Equal) => "E",
+            Some(Ordering::Greater) => "G",
+            None => "-",
+        };
+        let overflow = if self.overflow { "ON" } else { "OFF" };
+        write!(f, " rJ={} OV={} CI={}", self.j, overflow, cmp)
+    }
+}
+
+impl fmt::Display for InvalidIndexReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid index register {} (expected 1..={})",
+            self.0, MAX_INDEX_REGISTER
+        )
+    }
+}
+
+impl std::error::Error for InvalidIndexReg {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_reg_valid() {
+        for n in 1..=MAX_INDEX_REGISTER {
+            let reg = IndexReg::try_from(n).unwrap();
+            assert_eq!(reg.number(), n);
+            assert_eq!(u8::from(reg), n);
+        }
+    }
+
+    #[test]
+    fn test_index_reg_invalid() {
+        assert_eq!(IndexReg::try_from(0), Err(InvalidIndexReg(0)));
+        assert_eq!(
+            IndexReg::try_from(MAX_INDEX_REGISTER + 1),
+            Err(InvalidIndexReg(MAX_INDEX_REGISTER + 1))
+        );
+    }
+
+    #[test]
+    fn test_index_reg_extended() {
+        assert!(!IndexReg::try_from(STRICT_INDEX_REGISTERS)
+            .unwrap()
+            .is_extended());
+        assert!(IndexReg::try_from(STRICT_INDEX_REGISTERS + 1)
+            .unwrap()
+            .is_extended());
+    }
+
+    #[test]
+    fn test_index_reg_display() {
+        assert_eq!(IndexReg::try_from(3).unwrap().to_string(), "rI3");
+    }
+
+    #[test]
+    fn test_registers_display() {
+        let registers = Registers {
+            a: 5,
+            x: -1,
+            i: vec![2, 0],
+            j: 7,
+            overflow: true,
+            comparison: Some(Ordering::Less),
+        };
+        assert_eq!(
+            registers.to_string(),
+            "rA=5 rX=-1 rI1=2 rI2=0 rJ=7 OV=ON CI=L"
+        );
+    }
+}
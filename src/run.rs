@@ -0,0 +1,91 @@
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{EnergyModel, Instruction, MMix, TraceEvent, Tracer};
+
+type PeriodicCallback<'a> = Box<dyn FnMut(&MMix, u64) -> ControlFlow<()> + 'a>;
+
+#[derive(Default)]
+pub struct RunConfig<'a> {
+    pub(crate) periodic: Option<(u64, PeriodicCallback<'a>)>,
+    pub(crate) cancel: Option<&'a AtomicBool>,
+    pub(crate) energy: Option<Box<dyn EnergyModel + 'a>>,
+    pub(crate) tracer: Option<Box<dyn Tracer + 'a>>,
+}
+
+impl<'a> RunConfig<'a> {
+    pub fn new() -> Self {
+        Self {
+            periodic: None,
+            cancel: None,
+            energy: None,
+            tracer: None,
+        }
+    }
+
+    /// Call `callback` with the machine and the instruction count after every
+    /// `interval` instructions; returning `ControlFlow::Break` stops the run.
+    pub fn periodic(
+        mut self,
+        interval: u64,
+        callback: impl FnMut(&MMix, u64) -> ControlFlow<()> + 'a,
+    ) -> Self {
+        if interval > 0 {
+            self.periodic = Some((interval, Box::new(callback)));
+        }
+        self
+    }
+
+    /// Stop the run before the next instruction once `token` is set, e.g.
+    /// from another thread.
+    pub fn cancel_token(mut self, token: &'a AtomicBool) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Charge every executed instruction to `model`; the total is
+    /// reported in `RunSummary::energy`.
+    pub fn energy_model(mut self, model: impl EnergyModel + 'a) -> Self {
+        self.energy = Some(Box::new(model));
+        self
+    }
+
+    /// Report every executed instruction and its effects to `tracer`.
+    pub fn tracer(mut self, tracer: impl Tracer + 'a) -> Self {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
+
+    pub(crate) fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    pub(crate) fn trace(&mut self, event: TraceEvent) {
+        if let Some(tracer) = &mut self.tracer {
+            tracer.event(&event);
+        }
+    }
+
+    pub(crate) fn charge(
+        &mut self,
+        instruction: &Instruction,
+        addrs: impl Iterator<Item = u64>,
+    ) -> u64 {
+        match &mut self.energy {
+            Some(model) => model.charge(instruction, &addrs.collect::<Vec<_>>()),
+            None => 0,
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn tick(&mut self, mmix: &MMix, count: u64) -> ControlFlow<()> {
+        match &mut self.periodic {
+            Some((interval, callback)) if count.is_multiple_of(*interval) => callback(mmix, count),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use crate::Instruction;
+
+/// Instruction classes of TAOCP 1.3.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpcodeFamily {
+    Load,
+    Store,
+    Arithmetic,
+    AddressTransfer,
+    Comparison,
+    Jump,
+    InputOutput,
+    Miscellaneous,
+}
+
+pub const OPCODE_FAMILIES: [OpcodeFamily; 8] = [
+    OpcodeFamily::Load,
+    OpcodeFamily::Store,
+    OpcodeFamily::Arithmetic,
+    OpcodeFamily::AddressTransfer,
+    OpcodeFamily::Comparison,
+    OpcodeFamily::Jump,
+    OpcodeFamily::InputOutput,
+    OpcodeFamily::Miscellaneous,
+];
+
+impl Instruction {
+    pub fn family(&self) -> OpcodeFamily {
+        match self {
+            Instruction::LDA(..)
+            | Instruction::LDX(..)
+            | Instruction::LDI(..)
+            | Instruction::LDAN(..)
+            | Instruction::LDXN(..)
+            | Instruction::LDIN(..) => OpcodeFamily::Load,
+            Instruction::STA(..)
+            | Instruction::STX(..)
+            | Instruction::STI(..)
+            | Instruction::STJ(..)
+            | Instruction::STZ(..) => OpcodeFamily::Store,
+            Instruction::ADD(..) | Instruction::SUB(..) => OpcodeFamily::Arithmetic,
+            Instruction::ENTA(_)
+            | Instruction::ENTX(_)
+            | Instruction::ENTI(..)
+            | Instruction::ENNA(_)
+            | Instruction::ENNX(_)
+            | Instruction::ENNI(..)
+            | Instruction::INCA(_)
+            | Instruction::INCX(_)
+            | Instruction::INCI(..)
+            | Instruction::DECA(_)
+            | Instruction::DECX(_)
+            | Instruction::DECI(..) => OpcodeFamily::AddressTransfer,
+            Instruction::CMPA(..) | Instruction::CMPX(..) | Instruction::CMPI(..) => {
+                OpcodeFamily::Comparison
+            }
+            Instruction::JMP(_)
+            | Instruction::JSJ(_)
+            | Instruction::JOV(_)
+            | Instruction::JNOV(_)
+            | Instruction::JL(_)
+            | Instruction::JE(_)
+            | Instruction::JG(_)
+            | Instruction::JGE(_)
+            | Instruction::JNE(_)
+            | Instruction::JLE(_)
+            | Instruction::JA(..)
+            | Instruction::JX(..)
+            | Instruction::JAE(_)
+            | Instruction::JAO(_)
+            | Instruction::JXE(_)
+            | Instruction::JXO(_)
+            | Instruction::JI(..)
+            | Instruction::JBUS(..)
+            | Instruction::JRED(..) => OpcodeFamily::Jump,
+            Instruction::IN(..) | Instruction::OUT(..) | Instruction::IOC(..) => {
+                OpcodeFamily::InputOutput
+            }
+            Instruction::SLA(_)
+            | Instruction::SRA(_)
+            | Instruction::SLAX(_)
+            | Instruction::SRAX(_)
+            | Instruction::SLC(_)
+            | Instruction::SRC(_)
+            | Instruction::SLB(_)
+            | Instruction::SRB(_)
+            | Instruction::MOVE(..)
+            | Instruction::HLT
+            | Instruction::NOP => OpcodeFamily::Miscellaneous,
+            Instruction::Indexed(_, instruction) => instruction.family(),
+        }
+    }
+}
+
+/// Executed instructions counted by family.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpcodeStats {
+    counts: [u64; OPCODE_FAMILIES.len()],
+}
+
+impl OpcodeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, instruction: &Instruction) {
+        self.counts[instruction.family() as usize] += 1;
+    }
+
+    pub fn count(&self, family: OpcodeFamily) -> u64 {
+        self.counts[family as usize]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+impl fmt::Display for OpcodeFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpcodeFamily::Load => write!(f, "loads"),
+            OpcodeFamily::Store => write!(f, "stores"),
+            OpcodeFamily::Arithmetic => write!(f, "arithmetic"),
+            OpcodeFamily::AddressTransfer => write!(f, "address transfers"),
+            OpcodeFamily::Comparison => write!(f, "comparisons"),
+            OpcodeFamily::Jump => write!(f, "jumps"),
+            OpcodeFamily::InputOutput => write!(f, "input-output"),
+            OpcodeFamily::Miscellaneous => write!(f, "miscellaneous"),
+        }
+    }
+}
+
+impl fmt::Display for OpcodeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for family in OPCODE_FAMILIES {
+            writeln!(f, "{:>10} {}", self.count(family), family)?;
+        }
+        writeln!(f, "{:>10} total", self.total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Field;
+
+    #[test]
+    fn test_opcode_stats() {
+        let mut stats = OpcodeStats::new();
+        for instruction in [
+            Instruction::LDA(1, Field::WORD),
+            Instruction::LDX(2, Field::WORD),
+            Instruction::HLT,
+        ] {
+            stats.record(&instruction);
+        }
+        assert_eq!(stats.count(OpcodeFamily::Load), 2);
+        assert_eq!(stats.count(OpcodeFamily::Store), 0);
+        assert_eq!(stats.total(), 3);
+        assert!(stats.to_string().contains("         2 loads\n"));
+        assert!(stats.to_string().ends_with("         3 total\n"));
+    }
+}
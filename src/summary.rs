@@ -0,0 +1,239 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{Instruction, Registers, Word};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Halted,
+    EndOfProgram,
+    Stopped,
+    Cancelled,
+    InvalidInstruction,
+    /// An index register moved an address or count below zero.
+    InvalidAddress,
+    ExtendedInstruction,
+    OutsideLoadedCode,
+    /// Execution reached a +0 word that no loader wrote, such as memory
+    /// past the end of a program without a HLT.
+    UninitializedMemory,
+    /// The HLT of an `ASSERT` whose condition did not hold.
+    AssertionFailed,
+    /// `execute_loaded` on a machine with no code loaded and no entry
+    /// point set, so nothing ran.
+    NothingLoaded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoKind {
+    Input,
+    Output,
+    Control,
+}
+
+/// One IN, OUT or IOC executed during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoEvent {
+    /// Location of the instruction.
+    pub pc: usize,
+    pub unit: u8,
+    pub kind: IoKind,
+    /// The memory address of IN and OUT, or M for IOC.
+    pub address: i64,
+    /// The block read or written. Empty for IOC, and for an IN from a unit
+    /// with no more input.
+    pub words: Vec<Word>,
+}
+
+/// The message and source line of an `ASSERT`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assertion {
+    pub message: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub instructions: u64,
+    /// MIX time units charged for the run, as in TAOCP's timing table.
+    pub cycles: u64,
+    /// Memory words read or written by instructions.
+    pub mems: u64,
+    /// Total charged by the run's energy model, or 0 without one.
+    pub energy: u64,
+    pub wall_time: Duration,
+    pub mips: f64,
+    pub stop_reason: StopReason,
+    /// Location of the instruction that stopped the run, or of the next
+    /// one to execute.
+    pub pc: usize,
+    pub word: Option<Word>,
+    pub instruction: Option<Instruction>,
+    /// The registers when the run stopped.
+    pub registers: Option<Registers>,
+    /// The assertion that failed, if the run stopped at one.
+    pub assertion: Option<Assertion>,
+    /// Every IN, OUT and IOC of the run, in order.
+    pub io: Vec<IoEvent>,
+}
+
+impl RunSummary {
+    pub fn new(instructions: u64, wall_time: Duration, stop_reason: StopReason) -> Self {
+        let seconds = wall_time.as_secs_f64();
+        let mips = if seconds > 0.0 {
+            instructions as f64 / seconds / 1_000_000.0
+        } else {
+            0.0
+        };
+        Self {
+            instructions,
+            cycles: 0,
+            mems: 0,
+            energy: 0,
+            wall_time,
+            mips,
+            stop_reason,
+            pc: 0,
+            word: None,
+            instruction: None,
+            registers: None,
+            assertion: None,
+            io: Vec::new(),
+        }
+    }
+
+    pub fn cycles(mut self, cycles: u64) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    pub fn mems(mut self, mems: u64) -> Self {
+        self.mems = mems;
+        self
+    }
+
+    pub fn energy(mut self, energy: u64) -> Self {
+        self.energy = energy;
+        self
+    }
+
+    pub fn io(mut self, io: Vec<IoEvent>) -> Self {
+        self.io = io;
+        self
+    }
+
+    pub fn at(
+        mut self,
+        pc: usize,
+        word: Option<Word>,
+        instruction: Option<Instruction>,
+        registers: Registers,
+    ) -> Self {
+        self.pc = pc;
+        self.word = word;
+        self.instruction = instruction;
+        self.registers = Some(registers);
+        self
+    }
+
+    /// Report a halt as a failed assertion if the HLT belongs to one.
+    pub fn assertion(mut self, assertion: Option<&Assertion>) -> Self {
+        if let Some(assertion) = assertion.filter(|_| self.stop_reason == StopReason::Halted) {
+            self.stop_reason = StopReason::AssertionFailed;
+            self.assertion = Some(assertion.clone());
+        }
+        self
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Halted => write!(f, "halted"),
+            StopReason::EndOfProgram => write!(f, "end of program"),
+            StopReason::Stopped => write!(f, "stopped by callback"),
+            StopReason::Cancelled => write!(f, "cancelled"),
+            StopReason::InvalidInstruction => write!(f, "invalid instruction"),
+            StopReason::InvalidAddress => write!(f, "invalid address"),
+            StopReason::ExtendedInstruction => write!(f, "extended instruction in strict mode"),
+            StopReason::OutsideLoadedCode => write!(f, "outside loaded code"),
+            StopReason::UninitializedMemory => write!(f, "uninitialized memory"),
+            StopReason::AssertionFailed => write!(f, "assertion failed"),
+            StopReason::NothingLoaded => write!(f, "nothing loaded"),
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" at line {}", self.message, self.line)
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} instructions ({}u) in {:?} ({:.3} MIPS), {} at pc={}",
+            self.instructions, self.cycles, self.wall_time, self.mips, self.stop_reason, self.pc
+        )?;
+        if let Some(word) = self.word {
+            write!(f, " [{}]", word)?;
+        }
+        if let Some(instruction) = &self.instruction {
+            write!(f, " {}", instruction)?;
+        }
+        if let Some(assertion) = &self.assertion {
+            write!(f, " ({})", assertion)?;
+        }
+        if let Some(registers) = &self.registers {
+            write!(f, "; {}", registers)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_BYTE_SIZE;
+
+    #[test]
+    fn test_summary_mips() {
+        let summary = RunSummary::new(2_000_000, Duration::from_secs(2), StopReason::Halted);
+        assert_eq!(summary.mips, 1.0);
+        assert_eq!(
+            summary.to_string(),
+            "2000000 instructions (0u) in 2s (1.000 MIPS), halted at pc=0"
+        );
+    }
+
+    #[test]
+    fn test_summary_zero_time() {
+        let summary = RunSummary::new(10, Duration::ZERO, StopReason::EndOfProgram);
+        assert_eq!(summary.mips, 0.0);
+    }
+
+    #[test]
+    fn test_summary_display_state() {
+        let summary = RunSummary::new(3, Duration::from_secs(1), StopReason::Halted)
+            .cycles(13)
+            .at(
+                2,
+                Instruction::HLT.to_word(DEFAULT_BYTE_SIZE),
+                Some(Instruction::HLT),
+                Registers {
+                    a: 1,
+                    x: 0,
+                    i: vec![],
+                    j: 0,
+                    overflow: false,
+                    comparison: None,
+                },
+            );
+        assert_eq!(
+            summary.to_string(),
+            "3 instructions (13u) in 1s (0.000 MIPS), halted at pc=2 [+ 00 00 00 02 05] HLT; rA=1 rX=0 rJ=0 OV=OFF CI=-"
+        );
+    }
+}
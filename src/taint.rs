@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+
+use crate::{Field, Instruction, RegisterName};
+
+/// Addresses of the input words a value was computed from.
+pub type Origins = BTreeSet<u64>;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Shadow {
+    a: Origins,
+    x: Origins,
+    i: Vec<Origins>,
+    // None means the word still holds its input value
+    memory: Vec<Option<Origins>>,
+}
+
+impl Shadow {
+    pub(crate) fn new(memory_size: usize, index_registers: usize) -> Self {
+        Self {
+            a: Origins::new(),
+            x: Origins::new(),
+            i: vec![Origins::new(); index_registers + 1],
+            memory: vec![None; memory_size],
+        }
+    }
+
+    pub(crate) fn register(&self, reg: &RegisterName) -> Origins {
+        match reg {
+            RegisterName::A => self.a.clone(),
+            RegisterName::X => self.x.clone(),
+            RegisterName::I(n) => self.i[n.index()].clone(),
+            RegisterName::J => Origins::new(),
+        }
+    }
+
+    pub(crate) fn memory(&self, addr: u64) -> Origins {
+        match &self.memory[addr as usize] {
+            Some(origins) => origins.clone(),
+            None => Origins::from([addr]),
+        }
+    }
+
+    /// Mark words written by the host as fresh inputs.
+    pub(crate) fn input(&mut self, addr: u64, len: usize) {
+        let addr = addr as usize;
+        self.memory[addr..addr + len].fill(None);
+    }
+
+    pub(crate) fn copy(&mut self, dst: u64, src: u64, len: usize) {
+        let origins: Vec<Option<Origins>> = (src..src + len as u64)
+            .map(|addr| Some(self.memory(addr)))
+            .collect();
+        let dst = dst as usize;
+        self.memory[dst..dst + len].clone_from_slice(&origins);
+    }
+
+    // a partial store keeps the bytes outside the field, and their origins
+    fn store(&mut self, addr: u64, field: Field, mut origins: Origins) {
+        if field != Field::WORD {
+            origins.extend(self.memory(addr));
+        }
+        self.memory[addr as usize] = Some(origins);
+    }
+
+    // `effective` is `instruction` with its index register applied
+    pub(crate) fn propagate(&mut self, instruction: &Instruction, effective: &Instruction) {
+        // ENTA M,I and the like enter a value computed from rI
+        let entered = match instruction {
+            Instruction::Indexed(n, _) => self.i[n.index()].clone(),
+            _ => Origins::new(),
+        };
+        match effective {
+            Instruction::LDA(addr, _) | Instruction::LDAN(addr, _) => self.a = self.memory(*addr),
+            Instruction::LDX(addr, _) | Instruction::LDXN(addr, _) => self.x = self.memory(*addr),
+            Instruction::LDI(n, addr, _) | Instruction::LDIN(n, addr, _) => {
+                self.i[n.index()] = self.memory(*addr)
+            }
+            Instruction::STA(addr, field) => self.store(*addr, *field, self.a.clone()),
+            Instruction::STX(addr, field) => self.store(*addr, *field, self.x.clone()),
+            Instruction::STI(n, addr, field) => {
+                self.store(*addr, *field, self.i[n.index()].clone())
+            }
+            Instruction::STJ(addr, field) | Instruction::STZ(addr, field) => {
+                self.store(*addr, *field, Origins::new())
+            }
+            Instruction::ENTA(_) | Instruction::ENNA(_) => self.a = entered,
+            Instruction::ENTX(_) | Instruction::ENNX(_) => self.x = entered,
+            Instruction::ENTI(n, _) | Instruction::ENNI(n, _) => self.i[n.index()] = entered,
+            Instruction::INCA(_) | Instruction::DECA(_) => self.a.extend(entered),
+            Instruction::INCX(_) | Instruction::DECX(_) => self.x.extend(entered),
+            Instruction::INCI(n, _) | Instruction::DECI(n, _) => self.i[n.index()].extend(entered),
+            Instruction::ADD(addr, _) | Instruction::SUB(addr, _) => {
+                let origins = self.memory(*addr);
+                self.a.extend(origins);
+            }
+            // bytes cross between rA and rX
+            Instruction::SLAX(_)
+            | Instruction::SRAX(_)
+            | Instruction::SLC(_)
+            | Instruction::SRC(_)
+            | Instruction::SLB(_)
+            | Instruction::SRB(_) => {
+                self.a.extend(self.x.iter().copied());
+                self.x = self.a.clone();
+            }
+            // MOVE goes through MMix::copy, which tracks its words, and
+            // indexed instructions are resolved before they get here
+            Instruction::CMPA(..)
+            | Instruction::CMPX(..)
+            | Instruction::CMPI(..)
+            | Instruction::IN(..)
+            | Instruction::OUT(..)
+            | Instruction::IOC(..)
+            | Instruction::SLA(_)
+            | Instruction::SRA(_)
+            | Instruction::MOVE(..)
+            | Instruction::Indexed(..)
+            | Instruction::JMP(_)
+            | Instruction::JSJ(_)
+            | Instruction::JOV(_)
+            | Instruction::JNOV(_)
+            | Instruction::JL(_)
+            | Instruction::JE(_)
+            | Instruction::JG(_)
+            | Instruction::JGE(_)
+            | Instruction::JNE(_)
+            | Instruction::JLE(_)
+            | Instruction::JA(..)
+            | Instruction::JX(..)
+            | Instruction::JAE(_)
+            | Instruction::JAO(_)
+            | Instruction::JXE(_)
+            | Instruction::JXO(_)
+            | Instruction::JI(..)
+            | Instruction::JBUS(..)
+            | Instruction::JRED(..)
+            | Instruction::HLT
+            | Instruction::NOP => {}
+        }
+    }
+}
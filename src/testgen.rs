@@ -0,0 +1,198 @@
+//! Boundary test vectors for the arithmetic instructions, checked against
+//! an i128 oracle. MIX 1009 as simulated here has no MUL or DIV, so the
+//! instructions that add are all there is: ADD, SUB, INCA and DECA.
+
+use crate::{
+    Arithmetic, Field, Instruction, MMix, MachineConfig, Program, Word, DEFAULT_BYTE_SIZE, MAX_WORD,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Inc,
+    Dec,
+}
+
+pub const ARITH_OPS: [ArithOp; 4] = [ArithOp::Add, ArithOp::Sub, ArithOp::Inc, ArithOp::Dec];
+
+impl ArithOp {
+    // INCA and DECA take their operand from the two-byte address field
+    fn fits(self, operand: i64) -> bool {
+        let max_address = (DEFAULT_BYTE_SIZE as i64).pow(2) - 1;
+        match self {
+            ArithOp::Add | ArithOp::Sub => true,
+            ArithOp::Inc | ArithOp::Dec => operand.abs() <= max_address,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    pub op: ArithOp,
+    pub a: i64,
+    pub operand: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub result: i64,
+    pub overflow: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub vector: TestVector,
+    pub expected: Outcome,
+    pub actual: Outcome,
+}
+
+pub fn boundary_values() -> Vec<i64> {
+    let max = MAX_WORD;
+    vec![-max, -max + 1, -2, -1, 0, 1, 2, max - 1, max]
+}
+
+/// Every pair of boundary values for each operation, leaving out
+/// operands INCA and DECA cannot hold.
+pub fn vectors() -> Vec<TestVector> {
+    let values = boundary_values();
+    let mut vectors = Vec::new();
+    for op in ARITH_OPS {
+        for &a in &values {
+            for &operand in values.iter().filter(|&&operand| op.fits(operand)) {
+                vectors.push(TestVector { op, a, operand });
+            }
+        }
+    }
+    vectors
+}
+
+/// The result of one addition on a fresh machine, which is the same under
+/// both kinds of arithmetic since the overflow toggle starts off. It is
+/// worked out from the exact sum alone, without the simulator's words: a
+/// sum past `MAX_WORD` overflows and keeps its sign and the low digits of
+/// its magnitude.
+pub fn oracle(vector: &TestVector) -> Outcome {
+    let (a, operand) = (vector.a as i128, vector.operand as i128);
+    let exact = match vector.op {
+        ArithOp::Add | ArithOp::Inc => a + operand,
+        ArithOp::Sub | ArithOp::Dec => a - operand,
+    };
+    let limit = MAX_WORD as i128 + 1;
+    let magnitude = exact.abs() % limit;
+    Outcome {
+        result: (exact.signum() * magnitude) as i64,
+        overflow: exact.abs() >= limit,
+    }
+}
+
+pub fn simulate(vector: &TestVector, arithmetic: Arithmetic) -> Outcome {
+    const OPERAND: u64 = 0;
+    let instruction = match vector.op {
+        ArithOp::Add => Instruction::ADD(OPERAND, Field::WORD),
+        ArithOp::Sub => Instruction::SUB(OPERAND, Field::WORD),
+        ArithOp::Inc => Instruction::INCA(vector.operand),
+        ArithOp::Dec => Instruction::DECA(vector.operand),
+    };
+    let mut program = Program::new(&instruction.to_string());
+    program.parse();
+    let mut mmix = MMix::with_config(MachineConfig::new().with_arithmetic(arithmetic));
+    mmix.a = Word::from(vector.a);
+    mmix.memory
+        .set(OPERAND as usize, Word::from(vector.operand));
+    mmix.execute(&program);
+    Outcome {
+        result: mmix.a.into(),
+        overflow: mmix.overflow,
+    }
+}
+
+/// Run every boundary vector and report those where the simulator
+/// disagrees with the oracle.
+pub fn check_arithmetic(arithmetic: Arithmetic) -> Vec<Divergence> {
+    vectors()
+        .into_iter()
+        .filter_map(|vector| {
+            let expected = oracle(&vector);
+            let actual = simulate(&vector, arithmetic);
+            (expected != actual).then_some(Divergence {
+                vector,
+                expected,
+                actual,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_cover_ops() {
+        let n = boundary_values().len();
+        // INCA and DECA skip the four operands past the address field
+        assert_eq!(vectors().len(), 2 * n * n + 2 * n * (n - 4));
+        assert!(vectors().iter().any(|vector| vector.op == ArithOp::Dec
+            && vector.a == -MAX_WORD
+            && vector.operand == 2));
+    }
+
+    #[test]
+    fn test_oracle_overflow() {
+        let vector = TestVector {
+            op: ArithOp::Sub,
+            a: -2,
+            operand: MAX_WORD,
+        };
+        assert_eq!(
+            oracle(&vector),
+            Outcome {
+                result: -1,
+                overflow: true
+            }
+        );
+        let vector = TestVector {
+            op: ArithOp::Add,
+            a: 2,
+            operand: MAX_WORD,
+        };
+        assert_eq!(
+            oracle(&vector),
+            Outcome {
+                result: 1,
+                overflow: true
+            }
+        );
+        let vector = TestVector {
+            op: ArithOp::Dec,
+            a: -MAX_WORD,
+            operand: 1,
+        };
+        assert_eq!(
+            oracle(&vector),
+            Outcome {
+                result: 0,
+                overflow: true
+            }
+        );
+        let vector = TestVector {
+            op: ArithOp::Inc,
+            a: -2,
+            operand: 2,
+        };
+        assert_eq!(
+            oracle(&vector),
+            Outcome {
+                result: 0,
+                overflow: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_arithmetic() {
+        assert_eq!(check_arithmetic(Arithmetic::Mix), vec![]);
+        assert_eq!(check_arithmetic(Arithmetic::Relaxed), vec![]);
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::{Instruction, RegisterName, Word};
+
+/// What one executed instruction did, in the order it is reported: the
+/// instruction first, then each register and memory word it changed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    Execute {
+        pc: usize,
+        /// The instruction's word, if it has an encoding.
+        word: Option<Word>,
+        instruction: Instruction,
+        /// The instruction with its index register applied.
+        effective: Instruction,
+        /// The effective address M, if the instruction reads, writes or
+        /// jumps to memory.
+        address: Option<u64>,
+    },
+    Register {
+        reg: RegisterName,
+        old: i64,
+        new: i64,
+    },
+    Memory {
+        addr: u64,
+        old: Word,
+        new: Word,
+    },
+}
+
+pub trait Tracer {
+    fn event(&mut self, event: &TraceEvent);
+}
+
+/// Collects events, e.g. `RunConfig::new().tracer(&mut events)`.
+impl Tracer for Vec<TraceEvent> {
+    fn event(&mut self, event: &TraceEvent) {
+        self.push(event.clone());
+    }
+}
+
+impl<T: Tracer + ?Sized> Tracer for &mut T {
+    fn event(&mut self, event: &TraceEvent) {
+        (**self).event(event);
+    }
+}
+
+/// Formats a trace one line per instruction: the location, the raw word,
+/// the instruction and every register and memory word it changed, as in
+///
+/// ```text
+/// 0001 [+ 01 44 00 05 24] STA 100; M[100]=[+ 00 00 00 00 05]
+/// ```
+///
+/// The format does not change between runs, so the logs of two simulators
+/// can be diffed to find the first instruction where they disagree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceLog {
+    lines: Vec<String>,
+    // whether the last line has any writes yet
+    written: bool,
+}
+
+impl TraceLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    // add a write to the line of the instruction that made it
+    fn append(&mut self, write: String) {
+        if let Some(line) = self.lines.last_mut() {
+            line.push_str(if self.written { " " } else { "; " });
+            line.push_str(&write);
+            self.written = true;
+        }
+    }
+}
+
+impl Tracer for TraceLog {
+    fn event(&mut self, event: &TraceEvent) {
+        match event {
+            TraceEvent::Execute {
+                pc,
+                word,
+                instruction,
+                ..
+            } => {
+                let word = word.map_or("?".to_string(), |word| word.to_string());
+                self.lines
+                    .push(format!("{:04} [{}] {}", pc, word, instruction));
+                self.written = false;
+            }
+            TraceEvent::Register { reg, new, .. } => self.append(format!("{}={}", reg, new)),
+            TraceEvent::Memory { addr, new, .. } => self.append(format!("M[{}]=[{}]", addr, new)),
+        }
+    }
+}
+
+impl fmt::Display for TraceLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceEvent::Execute {
+                pc, instruction, ..
+            } => write!(f, "{:04}: {}", pc, instruction),
+            TraceEvent::Register { reg, old, new } => {
+                write!(f, "      {} {} -> {}", reg, old, new)
+            }
+            TraceEvent::Memory { addr, old, new } => {
+                write!(f, "      M[{}] {} -> {}", addr, old, new)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Field;
+
+    #[test]
+    fn test_trace_event_display() {
+        let event = TraceEvent::Execute {
+            pc: 3,
+            word: None,
+            instruction: Instruction::STA(100, Field::WORD),
+            effective: Instruction::STA(100, Field::WORD),
+            address: Some(100),
+        };
+        assert_eq!(event.to_string(), "0003: STA 100");
+        let event = TraceEvent::Memory {
+            addr: 100,
+            old: Word::default(),
+            new: Word::from(-5),
+        };
+        assert_eq!(
+            event.to_string(),
+            "      M[100] + 00 00 00 00 00 -> - 00 00 00 00 05"
+        );
+    }
+}
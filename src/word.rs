@@ -0,0 +1,349 @@
+use std::fmt;
+
+use crate::DEFAULT_BYTE_SIZE;
+
+pub const WORD_BYTES: usize = 5;
+
+/// A field specification (L:R) selecting bytes L..=R of a word, where
+/// byte 0 is the sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    left: u8,
+    right: u8,
+}
+
+impl Field {
+    /// (0:5), the whole word.
+    pub const WORD: Field = Field { left: 0, right: 5 };
+    /// (0:2), the sign and address bytes, the default for STJ.
+    pub const ADDRESS: Field = Field { left: 0, right: 2 };
+
+    pub fn new(left: u8, right: u8) -> Option<Self> {
+        (left <= right && right as usize <= WORD_BYTES).then_some(Self { left, right })
+    }
+
+    /// Decode the F byte 8L + R.
+    pub fn from_spec(spec: u8) -> Option<Self> {
+        Self::new(spec / 8, spec % 8)
+    }
+
+    pub fn spec(&self) -> u8 {
+        8 * self.left + self.right
+    }
+
+    pub fn left(&self) -> u8 {
+        self.left
+    }
+
+    pub fn right(&self) -> u8 {
+        self.right
+    }
+
+    /// The field of `value`, shifted right as a load would see it. The
+    /// sign is kept only if the field includes byte 0. Like the `i64`
+    /// conversions of `Word`, this assumes 64-value bytes.
+    pub fn extract(&self, value: i64) -> i64 {
+        if *self == Field::WORD {
+            return value;
+        }
+        Word::from(value).field(*self).into()
+    }
+
+    /// `target` with this field replaced by the low bytes of `source`, as
+    /// a store would leave it.
+    pub fn insert(&self, target: i64, source: i64) -> i64 {
+        if *self == Field::WORD {
+            return source;
+        }
+        let mut word = Word::from(target);
+        word.set_field(*self, Word::from(source));
+        word.into()
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}:{})", self.left, self.right)
+    }
+}
+
+/// A MIX word: a sign and five bytes.
+///
+/// The sign is kept apart from the bytes, so a word can be -0. Its
+/// numeric value depends on the byte size of the machine holding it; the
+/// `i64` conversions use the default of 64.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Word {
+    pub negative: bool,
+    pub bytes: [u8; WORD_BYTES],
+}
+
+impl Word {
+    /// The sign of `value` and the low five bytes of its magnitude, in
+    /// bytes of `byte_size` values.
+    pub fn from_value(value: i64, byte_size: u8) -> Self {
+        let size = byte_size as u64;
+        let mut magnitude = value.unsigned_abs();
+        let mut bytes = [0; WORD_BYTES];
+        for byte in bytes.iter_mut().rev() {
+            *byte = (magnitude % size) as u8;
+            magnitude /= size;
+        }
+        Self {
+            negative: value < 0,
+            bytes,
+        }
+    }
+
+    /// The signed value, reading the bytes as digits in base `byte_size`.
+    /// -0 is 0.
+    pub fn value(&self, byte_size: u8) -> i64 {
+        if self.negative {
+            -self.magnitude(byte_size)
+        } else {
+            self.magnitude(byte_size)
+        }
+    }
+
+    pub fn magnitude(&self, byte_size: u8) -> i64 {
+        self.bytes
+            .iter()
+            .fold(0, |value, &byte| value * byte_size as i64 + byte as i64)
+    }
+
+    /// +0 or -0.
+    pub fn is_zero(&self) -> bool {
+        self.bytes == [0; WORD_BYTES]
+    }
+
+    /// The same bytes with the other sign.
+    pub fn negated(self) -> Self {
+        Self {
+            negative: !self.negative,
+            ..self
+        }
+    }
+
+    /// Reduce an exact result to a word, keeping its sign and the low five
+    /// bytes of its magnitude. The flag is set if bytes were lost.
+    pub(crate) fn wrap(exact: i128, byte_size: u8) -> (Self, bool) {
+        let limit = (byte_size as i128).pow(WORD_BYTES as u32);
+        let magnitude = (exact.abs() % limit) as i64;
+        let mut word = Self::from_value(magnitude, byte_size);
+        word.negative = exact < 0;
+        (word, exact.abs() >= limit)
+    }
+
+    pub fn sign(&self) -> char {
+        if self.negative {
+            '-'
+        } else {
+            '+'
+        }
+    }
+
+    /// Load semantics: the selected bytes moved to the right of a new
+    /// word, with the sign if the field includes it and + otherwise.
+    pub fn field(&self, field: Field) -> Word {
+        let mut result = Word::default();
+        if field.left == 0 {
+            result.negative = self.negative;
+        }
+        let first = field.left.max(1) as usize;
+        let last = field.right as usize;
+        if first <= last {
+            let len = last - first + 1;
+            result.bytes[WORD_BYTES - len..].copy_from_slice(&self.bytes[first - 1..last]);
+        }
+        result
+    }
+
+    /// Store semantics: replace the selected bytes with the rightmost
+    /// bytes of `source`, and the sign if the field includes it.
+    pub fn set_field(&mut self, field: Field, source: Word) {
+        if field.left == 0 {
+            self.negative = source.negative;
+        }
+        let first = field.left.max(1) as usize;
+        let last = field.right as usize;
+        if first <= last {
+            let len = last - first + 1;
+            self.bytes[first - 1..last].copy_from_slice(&source.bytes[WORD_BYTES - len..]);
+        }
+    }
+
+    // the sign and bytes packed into one number, for hashing
+    pub(crate) fn bits(&self) -> u64 {
+        self.bytes
+            .iter()
+            .fold(self.negative as u64, |bits, &byte| bits << 8 | byte as u64)
+    }
+}
+
+/// Keeps the sign and the low five bytes of the magnitude.
+impl From<i64> for Word {
+    fn from(value: i64) -> Self {
+        Self::from_value(value, DEFAULT_BYTE_SIZE)
+    }
+}
+
+impl From<Word> for i64 {
+    fn from(word: Word) -> Self {
+        word.value(DEFAULT_BYTE_SIZE)
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.sign())?;
+        for byte in self.bytes {
+            write!(f, " {:02}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// rA and rX shifted as one ten-byte register, `count` bytes to the left
+/// (or right if negative). Both registers keep their signs.
+pub(crate) fn shift_pair(a: Word, x: Word, count: i64, circular: bool) -> (Word, Word) {
+    let width = 2 * WORD_BYTES;
+    let mut pair = [0; 2 * WORD_BYTES];
+    pair[..WORD_BYTES].copy_from_slice(&a.bytes);
+    pair[WORD_BYTES..].copy_from_slice(&x.bytes);
+    if circular {
+        pair.rotate_left(count.rem_euclid(width as i64) as usize);
+    } else {
+        let n = count.unsigned_abs().min(width as u64) as usize;
+        if count >= 0 {
+            pair.copy_within(n.., 0);
+            pair[width - n..].fill(0);
+        } else {
+            pair.copy_within(..width - n, n);
+            pair[..n].fill(0);
+        }
+    }
+    let mut a = a;
+    let mut x = x;
+    a.bytes.copy_from_slice(&pair[..WORD_BYTES]);
+    x.bytes.copy_from_slice(&pair[WORD_BYTES..]);
+    (a, x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(negative: bool, bytes: [u8; 5]) -> Word {
+        Word { negative, bytes }
+    }
+
+    #[test]
+    fn test_field_spec() {
+        assert_eq!(Field::from_spec(5), Some(Field::WORD));
+        assert_eq!(Field::new(1, 3).unwrap().spec(), 11);
+        assert_eq!(Field::new(4, 2), None);
+        assert_eq!(Field::new(0, 6), None);
+        assert_eq!(Field::new(1, 3).unwrap().to_string(), "(1:3)");
+    }
+
+    #[test]
+    fn test_word_round_trip() {
+        let w = word(true, [1, 2, 3, 4, 5]);
+        assert_eq!(Word::from(i64::from(w)), w);
+        assert_eq!(w.to_string(), "- 01 02 03 04 05");
+    }
+
+    #[test]
+    fn test_load_fields() {
+        // TAOCP 1.3.1: loading from - 01 16 03 05 04
+        let w = word(true, [1, 16, 3, 5, 4]);
+        let load = |l, r| w.field(Field::new(l, r).unwrap());
+        assert_eq!(load(0, 5), w);
+        assert_eq!(load(1, 5), word(false, [1, 16, 3, 5, 4]));
+        assert_eq!(load(3, 5), word(false, [0, 0, 3, 5, 4]));
+        assert_eq!(load(0, 3), word(true, [0, 0, 1, 16, 3]));
+        assert_eq!(load(4, 4), word(false, [0, 0, 0, 0, 5]));
+        assert_eq!(load(0, 0), word(true, [0, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_store_fields() {
+        // TAOCP 1.3.1: storing rA = + 06 07 08 09 00 into - 01 02 03 04 05
+        let source = word(false, [6, 7, 8, 9, 0]);
+        let store = |l, r| {
+            let mut target = word(true, [1, 2, 3, 4, 5]);
+            target.set_field(Field::new(l, r).unwrap(), source);
+            target
+        };
+        assert_eq!(store(0, 5), source);
+        assert_eq!(store(1, 5), word(true, [6, 7, 8, 9, 0]));
+        assert_eq!(store(5, 5), word(true, [1, 2, 3, 4, 0]));
+        assert_eq!(store(2, 2), word(true, [1, 0, 3, 4, 5]));
+        assert_eq!(store(2, 3), word(true, [1, 9, 0, 4, 5]));
+        assert_eq!(store(0, 1), word(false, [0, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_shift_pair() {
+        // TAOCP 1.3.1: starting from rA = + 1 2 3 4 5, rX = - 6 7 8 9 10
+        let a = word(false, [1, 2, 3, 4, 5]);
+        let x = word(true, [6, 7, 8, 9, 10]);
+        // SRAX 1
+        let (a, x) = shift_pair(a, x, -1, false);
+        assert_eq!(
+            (a, x),
+            (word(false, [0, 1, 2, 3, 4]), word(true, [5, 6, 7, 8, 9]))
+        );
+        // SLA 2
+        let (a, x) = (shift_pair(a, Word::default(), 2, false).0, x);
+        assert_eq!(a, word(false, [2, 3, 4, 0, 0]));
+        // SRC 4
+        let (a, x) = shift_pair(a, x, -4, true);
+        assert_eq!(
+            (a, x),
+            (word(false, [6, 7, 8, 9, 2]), word(true, [3, 4, 0, 0, 5]))
+        );
+        // SRA 2
+        let (a, x) = (shift_pair(a, Word::default(), -2, false).0, x);
+        assert_eq!(a, word(false, [0, 0, 6, 7, 8]));
+        // SLC 501
+        let (a, x) = shift_pair(a, x, 501, true);
+        assert_eq!(
+            (a, x),
+            (word(false, [0, 6, 7, 8, 3]), word(true, [4, 0, 0, 5, 0]))
+        );
+        assert_eq!(
+            shift_pair(a, x, 10, false),
+            (word(false, [0; 5]), word(true, [0; 5]))
+        );
+    }
+
+    #[test]
+    fn test_word_byte_size() {
+        let w = Word::from_value(-123_456_789, 100);
+        assert_eq!(w, word(true, [1, 23, 45, 67, 89]));
+        assert_eq!(w.value(100), -123_456_789);
+        assert_eq!(Word::from_value(100i64.pow(5) + 7, 100).value(100), 7);
+        let (wrapped, overflow) = Word::wrap(-(100i128.pow(5)) - 2, 100);
+        assert_eq!((wrapped.value(100), overflow), (-2, true));
+    }
+
+    #[test]
+    fn test_negative_zero() {
+        let zero = Word::default().negated();
+        assert!(zero.is_zero());
+        assert_eq!(zero.value(64), 0);
+        assert_ne!(zero, Word::default());
+        assert_eq!(zero.to_string(), "- 00 00 00 00 00");
+        assert_ne!(zero.bits(), Word::default().bits());
+    }
+
+    #[test]
+    fn test_extract_insert() {
+        const BYTE_SIZE: i64 = DEFAULT_BYTE_SIZE as i64;
+        let field = Field::new(4, 5).unwrap();
+        assert_eq!(field.extract(-(3 * BYTE_SIZE + 7)), 3 * BYTE_SIZE + 7);
+        assert_eq!(field.insert(-BYTE_SIZE.pow(4), 1), -(BYTE_SIZE.pow(4) + 1));
+        assert_eq!(Field::WORD.extract(i64::MIN), i64::MIN);
+    }
+}
@@ -0,0 +1,79 @@
+use checksmix::{
+    text_to_words, LinePrinter, MMix, OpcodeFamily, Program, RegisterName, StopReason, Word,
+    LINE_PRINTER,
+};
+
+const SOURCE: &str = "\
+* add up a table, save the sum and print a greeting. Cards keep no
+* signs, so every instruction is positive.
+TABLE EQU  100
+COUNT EQU  TABLE+3
+MSG   EQU  200
+START ENTA 0
+      ENT1 0
+LOOP  ADD  TABLE,1
+      ENT1 1,1
+      CMP1 COUNT
+      JL   LOOP
+      STA  300
+      OUT  MSG(18)
+DONE  HLT
+      END  START
+";
+
+const ORIGIN: u64 = 1000;
+
+fn words(values: &[i64]) -> Vec<Word> {
+    values.iter().map(|&v| Word::from(v)).collect()
+}
+
+#[test]
+fn test_punched_program_runs_on_another_machine() {
+    let mut program = Program::new(SOURCE);
+    program.parse();
+    assert_eq!(program.labels()["START"], 0);
+    assert_eq!(program.labels()["LOOP"], 2);
+    assert_eq!(program.labels()["DONE"], 8);
+    assert_eq!(program.equates()["COUNT"], 103);
+    assert_eq!(program.entry_point(), Some(0));
+
+    // punch the code, the table and the message, one card each
+    let code = program.assemble_at(ORIGIN);
+    let mut punch = MMix::new();
+    punch.write_memory(ORIGIN, &code).unwrap();
+    punch.write_memory(100, &words(&[10, 20, 30, 3])).unwrap();
+    punch
+        .write_memory(200, &text_to_words("HELLO MIX").unwrap())
+        .unwrap();
+    for addr in [ORIGIN, 100, 200] {
+        punch.punch_card(addr).unwrap();
+    }
+    let deck = punch.punched_deck().clone();
+    assert_eq!(deck.len(), 3);
+
+    let mut mmix = MMix::new();
+    mmix.load_deck(&deck);
+    for addr in [ORIGIN, 100, 200] {
+        assert!(mmix.read_card(addr).unwrap());
+    }
+    assert!(!mmix.read_card(400).unwrap());
+    assert_eq!(mmix.read_memory(ORIGIN, code.len()).unwrap(), code);
+
+    let start = ORIGIN + program.entry_point().unwrap();
+    let summary = mmix.execute_from_memory(start);
+    assert_eq!(summary.stop_reason, StopReason::Halted);
+    assert_eq!(summary.pc as u64, ORIGIN + program.labels()["DONE"]);
+    assert_eq!(mmix.register(RegisterName::A), 60);
+    assert_eq!(mmix.read_memory(300, 1).unwrap(), words(&[60]));
+    let printer = mmix.device::<LinePrinter>(LINE_PRINTER).unwrap();
+    assert_eq!(printer.text(), "HELLO MIX\n");
+
+    // ENTA and ENT1, three passes of the loop, then STA, OUT and HLT
+    assert_eq!(summary.instructions, 17);
+    let stats = mmix.opcode_stats();
+    assert_eq!(stats.total(), 17);
+    assert_eq!(stats.count(OpcodeFamily::Arithmetic), 3);
+    assert_eq!(stats.count(OpcodeFamily::Comparison), 3);
+    assert_eq!(stats.count(OpcodeFamily::Jump), 3);
+    assert_eq!(stats.count(OpcodeFamily::InputOutput), 1);
+}